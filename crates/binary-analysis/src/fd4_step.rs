@@ -1,19 +1,13 @@
-use std::{
-    ffi::OsString, marker::PhantomData, ops::Range, os::windows::ffi::OsStringExt, ptr::NonNull,
-};
+use std::{marker::PhantomData, ops::Range, ptr::NonNull};
 
-use pelite::{
-    pe::{Pe, Va},
-    Align,
-};
+use pelite::pe64::{Pe, Va};
 use rayon::{
     iter::ParallelIterator,
     slice::{ParallelSlice, ParallelSliceMut},
 };
-use regex::bytes::Regex;
 use thiserror::Error;
 
-use crate::pe::sections;
+use crate::{pe::sections, signature::Signature};
 
 pub type Fd4StepFunction = unsafe extern "C" fn(this: NonNull<()>);
 
@@ -42,6 +36,18 @@ impl<'a> Fd4StepTables<'a> {
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.inner.iter().map(|(name, _)| &**name)
+    }
+
     /// Find FD4 step functions in the assembly of the static initializers
     /// that construct the step tables.
     ///
@@ -54,7 +60,7 @@ impl<'a> Fd4StepTables<'a> {
         let [text, data, rdata] =
             sections(program, [".text", ".data", ".rdata"]).map_err(Fd4StepError::Section)?;
 
-        let image_base = program.image().as_ptr();
+        let image_base = program.optional_header().ImageBase;
 
         let text_virtual_range = text.virtual_range();
         let data_virtual_range = data.virtual_range();
@@ -65,38 +71,24 @@ impl<'a> Fd4StepTables<'a> {
         // mov    QWORD PTR [rip+??],rax
         // lea    rax,[rip+??]
         // mov    QWORD PTR [rip+??],rax
-        let re = Regex::new(
-            r"(?s-u)\x48\x8d\x05(.{4})\x48\x89\x05(.{4})\x48\x8d\x05(.{4})\x48\x89\x05(.{4})",
+        let signature = Signature::new(
+            "48 8D 05 [fn_src:rel32] 48 89 05 [fn_dst:rel32] 48 8D 05 [name_src:rel32] 48 89 05 [name_dst:rel32]",
         )
         .unwrap();
 
-        let mut step_fns = re
-            .captures_iter(program.get_section_bytes(text)?)
-            .filter_map(|c| {
-                let [fn_src, fn_dst, name_src, name_dst] = if program.align() == Align::File {
-                    let [Ok(fn_src), Ok(fn_dst), Ok(name_src), Ok(name_dst)] =
-                        c.extract().1.map(|disp32| unsafe {
-                            program
-                                .file_offset_to_rva(
-                                    disp32.as_ptr_range().end.offset_from_unsigned(image_base),
-                                )
-                                .map(|rva| {
-                                    let disp32 = i32::from_le_bytes(disp32.try_into().unwrap());
-                                    rva.wrapping_add(disp32 as u32)
-                                })
-                        })
-                    else {
-                        return None;
-                    };
-
-                    [fn_src, fn_dst, name_src, name_dst]
-                } else {
-                    c.extract().1.map(|disp32| unsafe {
-                        let rva = disp32.as_ptr_range().end.offset_from_unsigned(image_base) as u32;
-                        let disp32 = i32::from_le_bytes(disp32.try_into().unwrap());
-                        rva.wrapping_add(disp32 as u32)
-                    })
-                };
+        let text_va = program.rva_to_va(text.VirtualAddress)?;
+
+        let mut step_fns = signature
+            .scan(program.get_section_bytes(text)?, text_va)
+            .filter_map(|m| {
+                let rva = |name| Some(m.get(name)?.wrapping_sub(image_base) as u32);
+
+                let [fn_src, fn_dst, name_src, name_dst] = [
+                    rva("fn_src")?,
+                    rva("fn_dst")?,
+                    rva("name_src")?,
+                    rva("name_dst")?,
+                ];
 
                 if !fn_src.is_multiple_of(16)
                     || !name_src.is_multiple_of(8)
@@ -126,7 +118,7 @@ impl<'a> Fd4StepTables<'a> {
                     return None;
                 }
 
-                let name = OsString::from_wide(name).to_string_lossy().into_owned();
+                let name = String::from_utf16_lossy(name);
 
                 Some((name, program.derva::<Va>(fn_src).ok()?))
             })
@@ -190,7 +182,7 @@ impl<'a> Fd4StepTables<'a> {
                     return None;
                 }
 
-                let name = OsString::from_wide(name).to_string_lossy().into_owned();
+                let name = String::from_utf16_lossy(name);
 
                 Some((name, fn_dst))
             })
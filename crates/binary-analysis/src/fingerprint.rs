@@ -0,0 +1,181 @@
+//! Fingerprints that identify exact builds of game executables.
+
+use std::{fmt, io, num::ParseIntError, path::Path, str::FromStr, sync::LazyLock};
+
+use pelite::{
+    pe64::{Pe, PeFile},
+    FileMap,
+};
+use serde::Deserialize;
+use thiserror::Error;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Identifies a build of an executable by its link timestamp, the size of its image and a hash
+/// of its `.text` section.
+///
+/// Formatted as `<timestamp>-<image size>-<.text hash>` in hexadecimal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    pub timestamp: u32,
+    pub image_size: u32,
+    pub text_hash: u64,
+}
+
+/// A build of a game listed in the known builds table.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct KnownBuild {
+    pub game: String,
+    pub version: String,
+}
+
+#[derive(Debug, Error)]
+pub enum FingerprintError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Pelite(#[from] pelite::Error),
+
+    #[error("executable has no .text section")]
+    MissingText,
+}
+
+#[derive(Debug, Error)]
+#[error("`{fingerprint}` is not a fingerprint like 00000000-00000000-0000000000000000")]
+pub struct ParseFingerprintError {
+    fingerprint: String,
+    #[source]
+    source: Option<ParseIntError>,
+}
+
+#[derive(Deserialize)]
+struct KnownBuilds {
+    builds: Vec<KnownBuildEntry>,
+}
+
+#[derive(Deserialize)]
+struct KnownBuildEntry {
+    fingerprint: String,
+    #[serde(flatten)]
+    build: KnownBuild,
+}
+
+static KNOWN_BUILDS: LazyLock<Vec<(Fingerprint, KnownBuild)>> = LazyLock::new(|| {
+    let known_builds: KnownBuilds =
+        toml::from_str(include_str!("known_builds.toml")).expect("invalid known builds table");
+
+    known_builds
+        .builds
+        .into_iter()
+        .map(|entry| {
+            let fingerprint = entry.fingerprint.parse().expect("invalid known build");
+            (fingerprint, entry.build)
+        })
+        .collect()
+});
+
+impl Fingerprint {
+    /// Fingerprints an executable image, mapped or read from disk.
+    pub fn of<'a, P: Pe<'a>>(program: P) -> Result<Self, FingerprintError> {
+        let text = program
+            .section_headers()
+            .by_name(".text")
+            .ok_or(FingerprintError::MissingText)?;
+
+        // Files contain `SizeOfRawData` bytes of the section and mapped images `VirtualSize`
+        // bytes, only the bytes present in both are hashed for fingerprints to agree.
+        let len = text.VirtualSize.min(text.SizeOfRawData) as usize;
+        let bytes = program.get_section_bytes(text)?;
+
+        Ok(Self {
+            timestamp: program.file_header().TimeDateStamp,
+            image_size: program.optional_header().SizeOfImage,
+            text_hash: xxh3_64(&bytes[..len.min(bytes.len())]),
+        })
+    }
+
+    /// Fingerprints the executable at `path`.
+    pub fn read(path: &Path) -> Result<Self, FingerprintError> {
+        let map = FileMap::open(path)?;
+        Self::of(PeFile::from_bytes(&map)?)
+    }
+
+    /// Looks up the build with this fingerprint in the known builds table.
+    pub fn identify(&self) -> Option<&'static KnownBuild> {
+        KNOWN_BUILDS
+            .iter()
+            .find_map(|(fingerprint, build)| (fingerprint == self).then_some(build))
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:08x}-{:08x}-{:016x}",
+            self.timestamp, self.image_size, self.text_hash
+        )
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = ParseFingerprintError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |source| ParseFingerprintError {
+            fingerprint: s.to_owned(),
+            source,
+        };
+
+        let parts = s.trim().split('-').collect::<Vec<_>>();
+
+        let [timestamp, image_size, text_hash] = parts[..] else {
+            return Err(error(None));
+        };
+
+        if !s.trim().bytes().all(|b| b == b'-' || b.is_ascii_hexdigit()) {
+            return Err(error(None));
+        }
+
+        Ok(Self {
+            timestamp: u32::from_str_radix(timestamp, 16).map_err(|e| error(Some(e)))?,
+            image_size: u32::from_str_radix(image_size, 16).map_err(|e| error(Some(e)))?,
+            text_hash: u64::from_str_radix(text_hash, 16).map_err(|e| error(Some(e)))?,
+        })
+    }
+}
+
+impl fmt::Display for KnownBuild {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.game, self.version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::LazyLock;
+
+    use super::{Fingerprint, KNOWN_BUILDS};
+
+    #[test]
+    fn fingerprints_round_trip() {
+        let fingerprint = Fingerprint {
+            timestamp: 0x6823_1f0a,
+            image_size: 0x0a2b_c000,
+            text_hash: 0x0123_4567_89ab_cdef,
+        };
+
+        let formatted = fingerprint.to_string();
+        assert_eq!(formatted, "68231f0a-0a2bc000-0123456789abcdef");
+        assert_eq!(formatted.parse::<Fingerprint>().unwrap(), fingerprint);
+
+        for invalid in ["", "1-2", "1-2-3-4", "+1-2-3", "x-2-3", "100000000-2-3"] {
+            assert!(invalid.parse::<Fingerprint>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn known_builds_are_valid() {
+        LazyLock::force(&KNOWN_BUILDS);
+    }
+}
@@ -1,3 +1,7 @@
 pub mod fd4_step;
+pub mod fingerprint;
+pub mod patterns;
 pub mod pe;
 pub mod rtti;
+pub mod signature;
+pub mod version;
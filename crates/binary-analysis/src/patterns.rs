@@ -0,0 +1,195 @@
+//! Byte signatures shared by the mod host and `me3 analyze`, along with the rules they are looked
+//! up by so both find the same targets.
+//!
+//! Every pattern is a [`Signature`] with a single `rel32` capture for the call target or
+//! RIP-relative operand that is used by the host.
+
+use std::collections::BTreeMap;
+
+use pelite::pe64::Va;
+
+use crate::signature::{Signature, SignatureError};
+
+/// Matches a tail call to the getter of the system allocator (Dark Souls 3, Sekiro and ELDEN
+/// RING).
+pub const SYSTEM_ALLOCATOR: &str =
+    "E8 ?? ?? ?? ?? 48 8B 74 24 30 48 8B 5C 24 38 48 83 C4 20 5F E9 [rel32]";
+
+/// Matches a constructor storing the `CSMemoryImp` vtable, for executables without RTTI for it.
+pub const CS_MEMORY_VTABLE: &str =
+    "E8 ?? ?? ?? ?? 90 48 8D 05 [rel32] 48 89 03 C6 83 ?? 02 00 00 00 48 8B C3 48 83 C4 ?? 5B C3";
+
+/// Matches storing the first entry of the allocator table.
+pub const FIRST_ALLOCATOR: &str = "48 89 05 [rel32] 4C 8B C0 BA 08 00 00 00 8D 4A 08";
+
+/// Matches storing the last entry of the allocator table (Dark Souls 3).
+pub const LAST_ALLOCATOR_DS3: &str = "48 89 05 [rel32] 4C 8B C0 BA 08 00 00 00 8D 4A 78";
+
+/// Matches storing the last entry of the allocator table (Sekiro).
+pub const LAST_ALLOCATOR_SDT: &str = "48 89 05 [rel32] 4C 8B C0 BA 08 00 00 00 8D 4A 70";
+
+/// Matches storing candidates for the last entry of the allocator table (ELDEN RING), the
+/// highest address is the last entry.
+pub const LAST_ALLOCATOR_ER: &str =
+    "48 89 3D [rel32] C7 44 24 20 FF FF FF FF 45 33 C9 4C 8B C7 48 8D 15 ?? ?? ?? ??";
+
+/// Matches a call to the getter of the debug allocator (Dark Souls 3).
+pub const DEBUG_ALLOCATOR_DS3: &str =
+    "48 8B 1D ?? ?? ?? ?? 48 8B 0D ?? ?? ?? ?? E8 [rel32] 48 8B D0 45 33 C0 48 8B CB E8 ?? ?? ?? ?? E8 ?? ?? ?? ??";
+
+/// Matches calls to the getter of the debug allocator (Sekiro), the most common target is the
+/// getter.
+pub const DEBUG_ALLOCATOR_SDT: &str =
+    "E8 [rel32] 48 89 44 24 ?? 4C 8B C0 BA 08 00 00 00 B9 90 00 00 00 E8 ?? ?? ?? ??";
+
+// The bool property patterns match callsites of the boolean DLSystemProperty getter.
+//
+// In Dark Souls 3, Sekiro and ER, the getter takes in a reference to a DLString,
+// in later games, it was changed to a nul terminated UTF-16 string pointer.
+//
+// The patterns match loading the pointer to the `std::map` containing the property names
+// and their values loaded in RCX, the queried property name in RDX and true/false in R8B
+// as the default value in the case of the property missing from the map.
+//
+// They return a lot of matches, the most common target is the getter.
+
+/// Matches calls to the bool property getter (Dark Souls 3).
+pub const BOOL_PROPERTY_DS3: &str =
+    "48 8D 54 24 30 48 8B 0D ?? ?? ?? ?? E8 [rel32] (88 05 ?? ?? ?? ?? | 0F B6 D8) 48 83 7C 24 48 08 72 ??";
+
+/// Matches calls to the bool property getter (Sekiro and ELDEN RING).
+pub const BOOL_PROPERTY_SDT: &str =
+    "48 8D 54 24 30 48 8B 0D ?? ?? ?? ?? E8 [rel32] (88 05 ?? ?? ?? ?? | 0F B6 D8) 48 83 7C 24 50 08 72 ??";
+
+/// Matches calls to the bool property getter (Armored Core 6 and later games).
+pub const BOOL_PROPERTY_AC6: &str =
+    "(45 33 C0 | 41 B0 01) 48 8D 15 ?? ?? ?? ?? 48 8B 0D ?? ?? ?? ?? E8 [rel32]";
+
+/// Matches a call to `MountEbl`:
+///
+/// ```text
+/// mov    reg,QWORD PTR [rbp+??]
+/// mov    QWORD PTR [rsp+0x28],reg
+/// mov    QWORD PTR [rsp+0x20],reg
+/// mov    r9,QWORD PTR [rip+??]
+/// mov    rdx,reg
+/// mov    rcx,reg
+/// call   MountEbl
+/// movzx  ebx,al
+/// cmp    QWORD PTR [rbp/rsp+??],0x8
+/// ```
+pub const MOUNT_EBL: &str =
+    "48 8B 45 ?? 48 89 44 24 28 (48 | 4C) 89 (44 | 4C | 54 | 5C | 64 | 6C | 74 | 7C) 24 20 4C 8B 0D ?? ?? ?? ?? (48 | 49) 8B D0-D7 (48 | 49) 8B C8-CF E8 [rel32] 0F B6 D8 (48 83 7D ?? 08 | 48 83 7C 24 ?? 08) 72 ??";
+
+/// Matches a wrapper of `MountEbl`, as a fallback for [`MOUNT_EBL`].
+///
+/// Not nearly as reliable (matches an entire function as-is), but works on all current game
+/// builds:
+///
+/// ```text
+/// push   rbx
+/// sub    rsp,0x30
+/// mov    rax,QWORD PTR [rsp+0x70]
+/// mov    r10,r9
+/// mov    r9,QWORD PTR [rsp+0x60]
+/// mov    r11,r8
+/// mov    QWORD PTR [rsp+0x28],rax
+/// mov    rcx,rdx
+/// mov    rax,QWORD PTR [rsp+0x68]
+/// mov    r8,r10
+/// mov    rdx,r11
+/// mov    QWORD PTR [rsp+0x20],rax
+/// call   MountEbl
+/// add    rsp,0x30
+/// pop    rbx
+/// ret
+/// ```
+pub const MOUNT_EBL_ALT: &str =
+    "53 48 83 EC 30 48 8B 44 24 70 4D 8B D1 4C 8B 4C 24 60 4D 8B D8 48 89 44 24 28 48 8B CA 48 8B 44 24 68 4D 8B C2 49 8B D3 48 89 44 24 20 E8 [rel32] 48 83 C4 30 5B C3";
+
+/// The target of the only match of `pattern` in `haystack`, the first byte of which is at `base`.
+/// Matches with the same target count as one, see [`Signature::find`].
+pub fn find_target(pattern: &str, haystack: &[u8], base: Va) -> Result<Va, SignatureError> {
+    let signature = Signature::new(pattern).expect("invalid pattern");
+    let target = signature.find(haystack, base)?.nth(0);
+
+    Ok(target.expect("patterns capture their target"))
+}
+
+/// The target of `MountEbl` in the `.text` section `text`, the first byte of which is at `base`.
+///
+/// Only falls back to [`MOUNT_EBL_ALT`] if [`MOUNT_EBL`] has no matches, not if it is ambiguous.
+pub fn find_mount_ebl(text: &[u8], base: Va) -> Result<Va, SignatureError> {
+    match find_target(MOUNT_EBL, text, base) {
+        Err(SignatureError::NotFound(_)) => find_target(MOUNT_EBL_ALT, text, base),
+        result => result,
+    }
+}
+
+/// The most common target of the matches of `pattern`, for patterns that match many call sites
+/// of one function along with a few unrelated ones.
+pub fn most_common_target(pattern: &str, haystack: &[u8], base: Va) -> Option<Va> {
+    Signature::new(pattern)
+        .expect("invalid pattern")
+        .scan(haystack, base)
+        .filter_map(|m| m.nth(0))
+        .fold(BTreeMap::<_, usize>::new(), |mut counts, target| {
+            *counts.entry(target).or_default() += 1;
+            counts
+        })
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(target, _)| target)
+}
+
+/// The highest target of the matches of `pattern`.
+pub fn highest_target(pattern: &str, haystack: &[u8], base: Va) -> Option<Va> {
+    Signature::new(pattern)
+        .expect("invalid pattern")
+        .scan(haystack, base)
+        .filter_map(|m| m.nth(0))
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::signature::{Signature, SignatureError};
+
+    #[test]
+    fn patterns_parse() {
+        for pattern in [
+            super::SYSTEM_ALLOCATOR,
+            super::CS_MEMORY_VTABLE,
+            super::FIRST_ALLOCATOR,
+            super::LAST_ALLOCATOR_DS3,
+            super::LAST_ALLOCATOR_SDT,
+            super::LAST_ALLOCATOR_ER,
+            super::DEBUG_ALLOCATOR_DS3,
+            super::DEBUG_ALLOCATOR_SDT,
+            super::BOOL_PROPERTY_DS3,
+            super::BOOL_PROPERTY_SDT,
+            super::BOOL_PROPERTY_AC6,
+            super::MOUNT_EBL,
+            super::MOUNT_EBL_ALT,
+        ] {
+            Signature::new(pattern).unwrap();
+        }
+    }
+
+    #[test]
+    fn mount_ebl_falls_back_only_if_missing() {
+        let alt = super::MOUNT_EBL_ALT.replace("[rel32]", "00 00 00 00");
+        let alt = alt
+            .split_whitespace()
+            .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+            .collect::<Vec<_>>();
+
+        let target = super::find_mount_ebl(&alt, 0).unwrap();
+        assert_eq!(target, alt.len() as u64 - 6);
+
+        assert!(matches!(
+            super::find_mount_ebl(&[0x90; 16], 0),
+            Err(SignatureError::NotFound(_))
+        ));
+    }
+}
@@ -1,6 +1,29 @@
-use std::mem::MaybeUninit;
+use std::{io, mem::MaybeUninit, path::Path, slice};
 
-use pelite::pe::{headers::SectionHeader, Pe};
+use pelite::{
+    pe64::{headers::SectionHeader, Pe, PeFile, PeView},
+    FileMap,
+};
+use thiserror::Error;
+
+/// An executable read from disk and laid out the way the system loader maps it, without applying
+/// relocations.
+///
+/// Allows functions that expect a mapped image to analyze executables that are not loaded.
+pub struct MappedImage {
+    // Aligned for reading pointers out of the image.
+    inner: Box<[u64]>,
+    len: usize,
+}
+
+#[derive(Debug, Error)]
+pub enum MapError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Pelite(#[from] pelite::Error),
+}
 
 pub fn section<'a, P, S>(program: P, name: S) -> Result<&'a SectionHeader, S>
 where
@@ -29,3 +52,54 @@ where
     // SAFETY: all elements have been initialized or the function returned early.
     unsafe { Ok(result.map(|e| e.assume_init())) }
 }
+
+impl MappedImage {
+    /// Maps the executable at `path`.
+    pub fn read(path: &Path) -> Result<Self, MapError> {
+        let map = FileMap::open(path)?;
+        let map = map.as_ref();
+        let file = PeFile::from_bytes(map)?;
+
+        let len = file.optional_header().SizeOfImage as usize;
+        let mut inner = vec![0u64; len.div_ceil(size_of::<u64>())].into_boxed_slice();
+
+        // SAFETY: `inner` is at least `len` bytes long.
+        let image = unsafe { slice::from_raw_parts_mut(inner.as_mut_ptr().cast::<u8>(), len) };
+
+        let headers = file.optional_header().SizeOfHeaders as usize;
+        copy_clamped(image, 0, map.get(..headers).unwrap_or(map));
+
+        for section in file.section_headers() {
+            // Sections may be truncated in the file, or have padding that isn't mapped.
+            let file_range = section.file_range();
+            let len = file_range.len().min(section.VirtualSize as usize);
+
+            if let Some(bytes) = map.get(file_range.start as usize..) {
+                let bytes = &bytes[..len.min(bytes.len())];
+                copy_clamped(image, section.VirtualAddress as usize, bytes);
+            }
+        }
+
+        let image = Self { inner, len };
+
+        PeView::from_bytes(image.bytes())?;
+
+        Ok(image)
+    }
+
+    pub fn view(&self) -> PeView<'_> {
+        PeView::from_bytes(self.bytes()).expect("headers were validated when mapped")
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: `inner` is at least `len` bytes long.
+        unsafe { slice::from_raw_parts(self.inner.as_ptr().cast(), self.len) }
+    }
+}
+
+fn copy_clamped(dst: &mut [u8], offset: usize, src: &[u8]) {
+    if let Some(dst) = dst.get_mut(offset..) {
+        let len = src.len().min(dst.len());
+        dst[..len].copy_from_slice(&src[..len]);
+    }
+}
@@ -0,0 +1,509 @@
+//! Byte signatures in the style of IDA patterns, e.g. `48 8D 4C 24 ?? E8 [rel32] 84 C0`.
+//!
+//! A signature is a sequence of:
+//!
+//! - bytes in hexadecimal, e.g. `E8`,
+//! - wildcards matching any byte, `??` or `?`,
+//! - byte ranges, e.g. `48-4F`,
+//! - alternatives, e.g. `(74 ?? | 0F 84 ?? ?? ?? ??)`,
+//! - captures, e.g. `[rel32]` or `[name:rel32]`.
+//!
+//! Captures read a little-endian value from the matched bytes:
+//!
+//! - `rel8` and `rel32` are signed displacements relative to the end of the operand, like the
+//!   operands of calls, jumps and RIP-relative addressing, and are resolved to the address they
+//!   refer to.
+//! - `u8`, `u16`, `u32` and `u64` are read as is.
+//!
+//! Captures can't be part of alternatives.
+
+use std::{fmt, str::FromStr};
+
+use pelite::pe64::Va;
+use regex::bytes::Regex;
+use thiserror::Error;
+
+/// A compiled byte signature.
+#[derive(Clone, Debug)]
+pub struct Signature {
+    source: Box<str>,
+    regex: Regex,
+    anchored: Regex,
+    captures: Box<[Capture]>,
+}
+
+/// A match of a [`Signature`] with the values of its captures.
+#[derive(Clone, Debug)]
+pub struct Match<'s> {
+    signature: &'s Signature,
+    va: Va,
+    values: Box<[u64]>,
+}
+
+#[derive(Debug, Error)]
+pub enum SignatureError {
+    #[error("signature `{0}` returned no matches")]
+    NotFound(Box<str>),
+
+    #[error("signature `{signature}` is ambiguous, it returned {count} different matches")]
+    Ambiguous { signature: Box<str>, count: usize },
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParseSignatureError {
+    #[error("signature is empty")]
+    Empty,
+
+    #[error("invalid token `{0}`")]
+    InvalidToken(String),
+
+    #[error("unknown capture type `{0}`")]
+    UnknownCapture(String),
+
+    #[error("capture `{0}` is named more than once")]
+    DuplicateName(String),
+
+    #[error("captures can't be part of alternatives")]
+    CaptureInAlternative,
+
+    #[error("unbalanced `{0}`")]
+    Unbalanced(char),
+}
+
+#[derive(Clone, Debug)]
+struct Capture {
+    name: Option<Box<str>>,
+    kind: CaptureKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CaptureKind {
+    Rel8,
+    Rel32,
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl Signature {
+    pub fn new(signature: &str) -> Result<Self, ParseSignatureError> {
+        let mut pattern = String::new();
+        let mut captures = Vec::<Capture>::new();
+        let mut depth = 0usize;
+        let mut is_empty = true;
+
+        let mut chars = signature.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            match c {
+                c if c.is_whitespace() => continue,
+                '(' => {
+                    depth += 1;
+                    pattern.push_str("(?:");
+                }
+                ')' => {
+                    depth = depth
+                        .checked_sub(1)
+                        .ok_or(ParseSignatureError::Unbalanced(')'))?;
+                    pattern.push(')');
+                }
+                '|' if depth > 0 => pattern.push('|'),
+                '[' => {
+                    if depth > 0 {
+                        return Err(ParseSignatureError::CaptureInAlternative);
+                    }
+
+                    let end = signature[start..]
+                        .find(']')
+                        .map(|len| start + len)
+                        .ok_or(ParseSignatureError::Unbalanced('['))?;
+
+                    let capture = signature[start + 1..end].parse::<Capture>()?;
+
+                    if let Some(name) = &capture.name
+                        && captures.iter().any(|c| c.name.as_ref() == Some(name))
+                    {
+                        return Err(ParseSignatureError::DuplicateName(name.to_string()));
+                    }
+
+                    pattern.push_str(&format!("(.{{{}}})", capture.kind.len()));
+                    captures.push(capture);
+                    is_empty = false;
+
+                    while chars.next_if(|(i, _)| *i <= end).is_some() {}
+                }
+                _ => {
+                    let mut end = start + c.len_utf8();
+
+                    while let Some((i, c)) = chars.next_if(|(_, c)| {
+                        !c.is_whitespace() && !matches!(c, '(' | ')' | '|' | '[' | ']')
+                    }) {
+                        end = i + c.len_utf8();
+                    }
+
+                    pattern.push_str(&token_pattern(&signature[start..end])?);
+                    is_empty = false;
+                }
+            }
+        }
+
+        if depth > 0 {
+            return Err(ParseSignatureError::Unbalanced('('));
+        }
+
+        if is_empty {
+            return Err(ParseSignatureError::Empty);
+        }
+
+        // Validated by construction, barring size limits for unreasonably long signatures.
+        let regex = Regex::new(&format!("(?s-u){pattern}")).expect("invalid signature regex");
+        let anchored =
+            Regex::new(&format!(r"(?s-u)\A(?:{pattern})")).expect("invalid signature regex");
+
+        Ok(Self {
+            source: signature.trim().into(),
+            regex,
+            anchored,
+            captures: captures.into_boxed_slice(),
+        })
+    }
+
+    /// Finds all non-overlapping matches in `haystack`, the first byte of which is at `base`.
+    pub fn scan<'s, 'h>(
+        &'s self,
+        haystack: &'h [u8],
+        base: Va,
+    ) -> impl Iterator<Item = Match<'s>> + use<'s, 'h> {
+        self.regex
+            .captures_iter(haystack)
+            .map(move |c| self.resolve(&c, base))
+    }
+
+    /// Finds all non-overlapping matches in `haystack`, which is mapped in the address space of
+    /// the running process.
+    pub fn scan_mapped<'s, 'h>(
+        &'s self,
+        haystack: &'h [u8],
+    ) -> impl Iterator<Item = Match<'s>> + use<'s, 'h> {
+        self.scan(haystack, haystack.as_ptr() as Va)
+    }
+
+    /// Finds the only match in `haystack`, the first byte of which is at `base`.
+    ///
+    /// Matches whose captures resolve to the same values count as one, like several calls to the
+    /// same function, and the first of them is returned. Signatures without captures must match
+    /// exactly once.
+    pub fn find(&self, haystack: &[u8], base: Va) -> Result<Match<'_>, SignatureError> {
+        let mut matches = self.scan(haystack, base);
+
+        let first = matches
+            .next()
+            .ok_or_else(|| SignatureError::NotFound(self.source.clone()))?;
+
+        let mut different = Vec::<Match<'_>>::new();
+
+        for m in matches {
+            let is_same = |other: &Match<'_>| !self.captures.is_empty() && other.values == m.values;
+
+            if !is_same(&first) && !different.iter().any(is_same) {
+                different.push(m);
+            }
+        }
+
+        match different.len() {
+            0 => Ok(first),
+            others => Err(SignatureError::Ambiguous {
+                signature: self.source.clone(),
+                count: others + 1,
+            }),
+        }
+    }
+
+    /// Finds the only match in `haystack`, which is mapped in the address space of the running
+    /// process, by the same rules as [`Signature::find`].
+    pub fn find_mapped(&self, haystack: &[u8]) -> Result<Match<'_>, SignatureError> {
+        self.find(haystack, haystack.as_ptr() as Va)
+    }
+
+    /// Returns the match at the start of `haystack`, the first byte of which is at `base`.
+    pub fn match_at(&self, haystack: &[u8], base: Va) -> Option<Match<'_>> {
+        self.anchored
+            .captures(haystack)
+            .map(|c| self.resolve(&c, base))
+    }
+
+    fn resolve(&self, c: &regex::bytes::Captures<'_>, base: Va) -> Match<'_> {
+        let whole = c.get(0).unwrap();
+
+        let values = self
+            .captures
+            .iter()
+            .zip(c.iter().skip(1))
+            .map(|(capture, m)| {
+                // Captures are never optional.
+                let m = m.unwrap();
+                let end = base.wrapping_add(m.end() as Va);
+
+                capture.kind.read(m.as_bytes(), end)
+            })
+            .collect();
+
+        Match {
+            signature: self,
+            va: base.wrapping_add(whole.start() as Va),
+            values,
+        }
+    }
+}
+
+impl FromStr for Signature {
+    type Err = ParseSignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Match<'_> {
+    /// The address of the first matched byte.
+    pub fn va(&self) -> Va {
+        self.va
+    }
+
+    /// The values of all captures in order.
+    pub fn values(&self) -> &[u64] {
+        &self.values
+    }
+
+    /// The value of the `index`-th capture.
+    pub fn nth(&self, index: usize) -> Option<u64> {
+        self.values.get(index).copied()
+    }
+
+    /// The value of the capture named `name`.
+    pub fn get(&self, name: &str) -> Option<u64> {
+        let index = self
+            .signature
+            .captures
+            .iter()
+            .position(|c| c.name.as_deref() == Some(name))?;
+
+        self.nth(index)
+    }
+}
+
+impl FromStr for Capture {
+    type Err = ParseSignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, kind) = match s.split_once(':') {
+            Some((name, kind)) => (Some(name.trim()), kind.trim()),
+            None => (None, s.trim()),
+        };
+
+        if let Some(name) = name
+            && (name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_'))
+        {
+            return Err(ParseSignatureError::InvalidToken(format!("[{s}]")));
+        }
+
+        let kind = match kind {
+            "rel8" => CaptureKind::Rel8,
+            "rel32" => CaptureKind::Rel32,
+            "u8" => CaptureKind::U8,
+            "u16" => CaptureKind::U16,
+            "u32" => CaptureKind::U32,
+            "u64" => CaptureKind::U64,
+            kind => return Err(ParseSignatureError::UnknownCapture(kind.to_owned())),
+        };
+
+        Ok(Self {
+            name: name.map(Into::into),
+            kind,
+        })
+    }
+}
+
+impl CaptureKind {
+    fn len(self) -> usize {
+        match self {
+            Self::Rel8 | Self::U8 => 1,
+            Self::U16 => 2,
+            Self::Rel32 | Self::U32 => 4,
+            Self::U64 => 8,
+        }
+    }
+
+    fn read(self, bytes: &[u8], end: Va) -> u64 {
+        let mut buf = [0; 8];
+        buf[..bytes.len()].copy_from_slice(bytes);
+
+        let value = u64::from_le_bytes(buf);
+
+        match self {
+            Self::Rel8 => end.wrapping_add_signed(value as i8 as i64),
+            Self::Rel32 => end.wrapping_add_signed(value as i32 as i64),
+            Self::U8 | Self::U16 | Self::U32 | Self::U64 => value,
+        }
+    }
+}
+
+fn token_pattern(token: &str) -> Result<String, ParseSignatureError> {
+    let invalid = || ParseSignatureError::InvalidToken(token.to_owned());
+
+    let byte = |s: &str| {
+        (s.len() == 2)
+            .then(|| u8::from_str_radix(s, 16).ok())
+            .flatten()
+            .ok_or_else(invalid)
+    };
+
+    match token {
+        "?" | "??" => Ok(".".to_owned()),
+        token => match token.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (byte(first)?, byte(last)?);
+
+                if first > last {
+                    return Err(invalid());
+                }
+
+                Ok(format!(r"[\x{first:02x}-\x{last:02x}]"))
+            }
+            None => Ok(format!(r"\x{:02x}", byte(token)?)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParseSignatureError, Signature, SignatureError};
+
+    const BASE: u64 = 0x1_4000_1000;
+
+    #[test]
+    fn resolves_captures() {
+        let signature = Signature::new("48 8D 4C 24 ?? E8 [call:rel32] 84 C0 [u8]").unwrap();
+
+        // lea rcx,[rsp+0x30]; call -0x10; test al,al
+        let haystack = [
+            0x90, 0x90, 0x48, 0x8d, 0x4c, 0x24, 0x30, 0xe8, 0xf0, 0xff, 0xff, 0xff, 0x84, 0xc0,
+            0x74,
+        ];
+
+        let m = signature.find(&haystack, BASE).unwrap();
+
+        assert_eq!(m.va(), BASE + 2);
+        assert_eq!(m.get("call"), Some(BASE + 12 - 0x10));
+        assert_eq!(m.nth(1), Some(0x74));
+        assert_eq!(m.get("missing"), None);
+        assert_eq!(m.values().len(), 2);
+    }
+
+    #[test]
+    fn matches_alternatives_and_ranges() {
+        let signature = Signature::new("E8 [rel32] (74 ?|0F 84 ? ? ? ?) 48-4F 83").unwrap();
+
+        let short = [0xe8, 0, 0, 0, 0, 0x74, 0x10, 0x49, 0x83];
+        let long = [0xe8, 1, 0, 0, 0, 0x0f, 0x84, 1, 2, 3, 4, 0x4f, 0x83];
+        let outside = [0xe8, 0, 0, 0, 0, 0x74, 0x10, 0x50, 0x83];
+
+        assert_eq!(signature.find(&short, BASE).unwrap().nth(0), Some(BASE + 5));
+        assert_eq!(signature.find(&long, BASE).unwrap().nth(0), Some(BASE + 6));
+        assert!(signature.find(&outside, BASE).is_err());
+    }
+
+    #[test]
+    fn reports_ambiguity() {
+        let signature = Signature::new("C3 CC").unwrap();
+
+        assert!(matches!(
+            signature.find(&[0xc3, 0xcc, 0x90, 0xc3, 0xcc], 0),
+            Err(SignatureError::Ambiguous { count: 2, .. })
+        ));
+
+        assert!(matches!(
+            signature.find(&[0xc3, 0x90], 0),
+            Err(SignatureError::NotFound(_))
+        ));
+
+        assert_eq!(signature.scan(&[0xc3, 0xcc, 0xc3, 0xcc], 0).count(), 2);
+
+        // Two calls to the same function and one to another.
+        let call = Signature::new("E8 [rel32]").unwrap();
+        let haystack = [0xe8, 5, 0, 0, 0, 0xe8, 0, 0, 0, 0, 0xe8, 1, 0, 0, 0];
+
+        assert_eq!(call.find(&haystack[..10], BASE).unwrap().va(), BASE);
+        assert_eq!(
+            call.find(&haystack[..10], BASE).unwrap().nth(0),
+            Some(BASE + 10)
+        );
+        assert!(matches!(
+            call.find(&haystack, BASE),
+            Err(SignatureError::Ambiguous { count: 2, .. })
+        ));
+
+        let haystack = [0x90, 0xc3, 0xcc, 0xc3, 0xcc];
+        assert!(matches!(
+            signature.find_mapped(&haystack),
+            Err(SignatureError::Ambiguous { count: 2, .. })
+        ));
+        assert_eq!(
+            signature.find_mapped(&haystack[..3]).unwrap().va(),
+            haystack[1..].as_ptr() as u64
+        );
+    }
+
+    #[test]
+    fn matches_at_start() {
+        let signature = Signature::new("32 C0 C3").unwrap();
+
+        assert!(signature.match_at(&[0x32, 0xc0, 0xc3, 0x90], 0).is_some());
+        assert!(signature.match_at(&[0x90, 0x32, 0xc0, 0xc3], 0).is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_signatures() {
+        let error = |s: &str| Signature::new(s).unwrap_err();
+
+        assert_eq!(error(""), ParseSignatureError::Empty);
+        assert_eq!(
+            error("4"),
+            ParseSignatureError::InvalidToken("4".to_owned())
+        );
+        assert_eq!(
+            error("GG"),
+            ParseSignatureError::InvalidToken("GG".to_owned())
+        );
+        assert_eq!(
+            error("4F-48"),
+            ParseSignatureError::InvalidToken("4F-48".to_owned())
+        );
+        assert_eq!(
+            error("E8 [rel16]"),
+            ParseSignatureError::UnknownCapture("rel16".to_owned())
+        );
+        assert_eq!(
+            error("[a:u8] [a:u8]"),
+            ParseSignatureError::DuplicateName("a".to_owned())
+        );
+        assert_eq!(
+            error("(E8 [rel32])"),
+            ParseSignatureError::CaptureInAlternative
+        );
+        assert_eq!(error("(E8"), ParseSignatureError::Unbalanced('('));
+        assert_eq!(error("E8)"), ParseSignatureError::Unbalanced(')'));
+        assert_eq!(error("E8 [rel32"), ParseSignatureError::Unbalanced('['));
+        assert_eq!(
+            error("E8 | E9"),
+            ParseSignatureError::InvalidToken("|".to_owned())
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Versions read from the version resource of executables.
+
+use std::{fmt, io, num::ParseIntError, path::Path, str::FromStr};
+
+use pelite::{
+    image::VS_VERSION,
+    pe64::{Pe, PeFile},
+    resources::FindError,
+    FileMap,
+};
+use thiserror::Error;
+
+/// A version number with up to four parts, like `1.16.0.0`. Parts that are left out are zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version([u16; 4]);
+
+/// The versions stored in the version resource of an executable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExecutableVersion {
+    pub file: Version,
+    pub product: Version,
+}
+
+#[derive(Debug, Error)]
+pub enum VersionError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Pelite(#[from] pelite::Error),
+
+    #[error("executable has no version resource")]
+    Missing,
+
+    #[error("version resource is corrupt: {0}")]
+    Resources(FindError),
+}
+
+#[derive(Debug, Error)]
+#[error("`{version}` is not a version number like 1.2.0")]
+pub struct ParseVersionError {
+    version: String,
+    #[source]
+    source: Option<ParseIntError>,
+}
+
+impl Version {
+    pub const fn new(major: u16, minor: u16, patch: u16, build: u16) -> Self {
+        Self([major, minor, patch, build])
+    }
+}
+
+impl From<VS_VERSION> for Version {
+    fn from(version: VS_VERSION) -> Self {
+        Self::new(version.Major, version.Minor, version.Patch, version.Build)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [major, minor, patch, build] = self.0;
+        write!(f, "{major}.{minor}.{patch}.{build}")
+    }
+}
+
+impl FromStr for Version {
+    type Err = ParseVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |source| ParseVersionError {
+            version: s.to_owned(),
+            source,
+        };
+
+        let mut parts = [0; 4];
+
+        for (index, part) in s.trim().split('.').enumerate() {
+            let slot = parts.get_mut(index).ok_or_else(|| error(None))?;
+
+            // `u16::from_str` accepts a leading `+`.
+            if !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(error(None));
+            }
+
+            *slot = part.parse().map_err(|e| error(Some(e)))?;
+        }
+
+        Ok(Self(parts))
+    }
+}
+
+impl ExecutableVersion {
+    /// Reads the versions from the version resource of a mapped executable image.
+    pub fn from_pe<'a, P: Pe<'a>>(program: P) -> Result<Self, VersionError> {
+        let version_info = program.resources()?.version_info().map_err(|e| match e {
+            FindError::NotFound => VersionError::Missing,
+            e => VersionError::Resources(e),
+        })?;
+
+        let fixed = version_info.fixed().ok_or(VersionError::Missing)?;
+
+        Ok(Self {
+            file: fixed.dwFileVersion.into(),
+            product: fixed.dwProductVersion.into(),
+        })
+    }
+
+    /// Reads the versions from the version resource of the executable at `path`.
+    pub fn read(path: &Path) -> Result<Self, VersionError> {
+        let map = FileMap::open(path)?;
+        Self::from_pe(PeFile::from_bytes(&map)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Version;
+
+    #[test]
+    fn parses_and_compares_versions() {
+        let version = |s: &str| s.parse::<Version>().unwrap();
+
+        assert_eq!(version("1.16"), Version::new(1, 16, 0, 0));
+        assert_eq!(version("1.16.1.0").to_string(), "1.16.1.0");
+        assert!(version("1.9") < version("1.16"));
+        assert!(version("1.16") < version("1.16.0.1"));
+        assert_eq!(version("1.2"), version("1.2.0.0"));
+
+        for invalid in ["", "1..2", "1.2.3.4.5", "v1.2", "1.+2", "1.70000"] {
+            assert!(invalid.parse::<Version>().is_err(), "{invalid}");
+        }
+    }
+}
@@ -1,7 +1,9 @@
+use analyze::AnalyzeArgs;
 use clap::*;
 use launch::LaunchArgs;
 use profile::ProfileCommands;
 
+pub mod analyze;
 pub mod info;
 pub mod launch;
 pub mod profile;
@@ -23,6 +25,10 @@ pub enum Commands {
     #[clap(subcommand, disable_version_flag = true)]
     Profile(ProfileCommands),
 
+    /// Check whether me3 finds what it needs in a game executable, without launching the game.
+    #[clap(disable_version_flag = true)]
+    Analyze(AnalyzeArgs),
+
     #[cfg(target_os = "windows")]
     #[clap(hide = true)]
     AddToPath,
@@ -0,0 +1,248 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use color_eyre::{
+    eyre::{eyre, OptionExt},
+    owo_colors::OwoColorize,
+};
+use me3_binary_analysis::{
+    fd4_step::Fd4StepTables,
+    fingerprint::Fingerprint,
+    patterns,
+    pe::{self, MappedImage},
+    rtti,
+    signature::SignatureError,
+    version::ExecutableVersion,
+};
+use me3_mod_protocol::{
+    game::{GameDefinition, GameDefinitions},
+    Game,
+};
+use pelite::pe64::{Pe, PeObject, PeView, Va};
+
+use crate::{config::Config, output::OutputBuilder};
+
+#[derive(Args, Debug)]
+pub struct AnalyzeArgs {
+    /// Path to the game executable to inspect.
+    exe: PathBuf,
+
+    /// Short name of the game the executable belongs to, detected from the name of the
+    /// executable by default.
+    #[clap(short('g'), long)]
+    game: Option<String>,
+}
+
+/// Finds the call targets and RIP-relative operands captured by signatures in an unloaded image,
+/// by the same rules as the mod host.
+struct Scanner<'a> {
+    image: &'a [u8],
+    image_base: Va,
+    text: &'a [u8],
+    text_base: Va,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(view: PeView<'a>) -> color_eyre::Result<Self> {
+        let text = pe::section(view, ".text").map_err(|_| eyre!(".text section is missing"))?;
+
+        Ok(Self {
+            image: view.image(),
+            image_base: view.optional_header().ImageBase,
+            text: view.get_section_bytes(text)?,
+            text_base: view.rva_to_va(text.VirtualAddress)?,
+        })
+    }
+
+    fn find(&self, pattern: &str) -> Result<Va, SignatureError> {
+        patterns::find_target(pattern, self.text, self.text_base)
+    }
+
+    fn find_in_image(&self, pattern: &str) -> Result<Va, SignatureError> {
+        patterns::find_target(pattern, self.image, self.image_base)
+    }
+
+    fn mount_ebl(&self) -> Result<Va, SignatureError> {
+        patterns::find_mount_ebl(self.text, self.text_base)
+    }
+
+    fn most_common(&self, pattern: &str) -> Option<Va> {
+        patterns::most_common_target(pattern, self.text, self.text_base)
+    }
+
+    fn highest_in_image(&self, pattern: &str) -> Option<Va> {
+        patterns::highest_target(pattern, self.image, self.image_base)
+    }
+}
+
+fn detect_game<'a>(
+    games: &'a GameDefinitions,
+    args: &AnalyzeArgs,
+) -> color_eyre::Result<&'a GameDefinition> {
+    if let Some(name) = &args.game {
+        return games
+            .find(name)
+            .ok_or_else(|| eyre!("unknown game {name:?}"));
+    }
+
+    let file_name = args
+        .exe
+        .file_name()
+        .ok_or_eyre("executable path has no file name")?;
+
+    games
+        .iter()
+        .find(|game| {
+            game.executable
+                .file_name()
+                .is_some_and(|name| name.eq_ignore_ascii_case(file_name))
+        })
+        .ok_or_else(|| eyre!("unable to tell which game {file_name:?} belongs to, use --game"))
+}
+
+fn format_check<T: ToString>(result: Option<T>) -> String {
+    match result {
+        Some(detail) => format!("{} ({})", "Found".green(), detail.to_string()),
+        None => "Not found".red().to_string(),
+    }
+}
+
+fn format_va(va: Va) -> String {
+    format!("{va:#x}")
+}
+
+/// Looks for everything the mod host needs to find in an executable, without launching it.
+#[tracing::instrument(err, skip_all)]
+pub fn analyze(config: Config, args: AnalyzeArgs) -> color_eyre::Result<()> {
+    let games = config.games()?;
+    let game = detect_game(&games, &args)?;
+
+    let image = MappedImage::read(&args.exe)?;
+    let view = image.view();
+    let scanner = Scanner::new(view)?;
+
+    let mut output = OutputBuilder::new(args.exe.display());
+
+    output.property("Game", &game.title);
+
+    match ExecutableVersion::from_pe(view) {
+        Ok(version) => output.property("Version", version.product),
+        Err(e) => output.property("Version", format!("unknown ({e})").yellow()),
+    }
+
+    let fingerprint = Fingerprint::of(view)?;
+    let build = fingerprint
+        .identify()
+        .map_or_else(|| "unknown build".yellow().to_string(), |b| b.to_string());
+
+    output.property("Build", build);
+    output.property("Fingerprint", fingerprint);
+
+    let class_map = rtti::classes(view)?;
+    let step_tables = Fd4StepTables::from_static_initializers(view)?;
+
+    let mut checks = vec![
+        (
+            "RTTI classes",
+            (!class_map.is_empty()).then(|| class_map.len().to_string()),
+        ),
+        (
+            "Step table entries",
+            (!step_tables.is_empty()).then(|| step_tables.len().to_string()),
+        ),
+    ];
+
+    let engine = game.engine;
+
+    if engine >= Game::DarkSouls3 {
+        // The device manager is only constructed at runtime, from the file step that is hooked to
+        // find it.
+        let file_step = step_tables
+            .names()
+            .find(|name| matches!(*name, "CSFileStep::STEP_Init" | "SprjFileStep::STEP_Init"));
+
+        checks.push((
+            "Device manager",
+            file_step.map(|step| format!("via {step}")),
+        ));
+
+        checks.push(("MountEbl", scanner.mount_ebl().ok().map(format_va)));
+
+        let bool_property = match engine {
+            Game::DarkSouls3 => patterns::BOOL_PROPERTY_DS3,
+            Game::Sekiro | Game::EldenRing => patterns::BOOL_PROPERTY_SDT,
+            _ => patterns::BOOL_PROPERTY_AC6,
+        };
+
+        checks.push((
+            "Bool property getter",
+            scanner.most_common(bool_property).map(format_va),
+        ));
+    }
+
+    if matches!(engine, Game::DarkSouls3 | Game::Sekiro | Game::EldenRing) {
+        checks.push((
+            "System allocator",
+            scanner.find(patterns::SYSTEM_ALLOCATOR).ok().map(format_va),
+        ));
+
+        let memory_vtable = ["CS::CSMemoryImp", "NS_SPRJ::CSMemoryImp"]
+            .into_iter()
+            .find(|name| class_map.contains_key(*name))
+            .map(|name| format!("RTTI for {name}"))
+            .or_else(|| {
+                scanner
+                    .find_in_image(patterns::CS_MEMORY_VTABLE)
+                    .ok()
+                    .map(format_va)
+            });
+
+        checks.push(("CSMemoryImp vtable", memory_vtable));
+
+        let last_allocator = match engine {
+            Game::DarkSouls3 => patterns::LAST_ALLOCATOR_DS3,
+            Game::Sekiro => patterns::LAST_ALLOCATOR_SDT,
+            _ => patterns::LAST_ALLOCATOR_ER,
+        };
+
+        let allocator_table = scanner
+            .find_in_image(patterns::FIRST_ALLOCATOR)
+            .ok()
+            .zip(scanner.highest_in_image(last_allocator))
+            .filter(|(first, last)| first <= last)
+            .map(|(first, last)| format!("{} entries", (last - first) / 8 + 1));
+
+        checks.push(("Allocator table", allocator_table));
+
+        let debug_allocator = match engine {
+            Game::DarkSouls3 => scanner
+                .find(patterns::DEBUG_ALLOCATOR_DS3)
+                .ok()
+                .map(format_va),
+            Game::Sekiro => scanner
+                .most_common(patterns::DEBUG_ALLOCATOR_SDT)
+                .map(format_va),
+            _ => class_map
+                .contains_key("CS::CSGraphicsImp")
+                .then(|| "RTTI for CS::CSGraphicsImp".to_owned()),
+        };
+
+        checks.push(("Debug allocator", debug_allocator));
+    }
+
+    let missing = checks.iter().filter(|(_, result)| result.is_none()).count();
+
+    output.section("Checks", |builder| {
+        for (name, result) in &checks {
+            builder.property(name, format_check(result.as_ref()));
+        }
+    });
+
+    print!("{}", output.build());
+
+    if missing > 0 {
+        return Err(eyre!("{missing} of {} checks failed", checks.len()));
+    }
+
+    Ok(())
+}
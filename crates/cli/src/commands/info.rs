@@ -1,6 +1,7 @@
 use color_eyre::owo_colors::OwoColorize;
+use me3_binary_analysis::{fingerprint::Fingerprint, version::ExecutableVersion};
 
-use crate::{config::Config, output::OutputBuilder};
+use crate::{commands::launch::game_exe_path, config::Config, output::OutputBuilder};
 
 fn format_path<P: AsRef<std::path::Path>>(path: Option<P>) -> String {
     match path {
@@ -20,8 +21,12 @@ fn format_status(status: bool) -> String {
 pub fn info(config: Config) -> color_eyre::Result<()> {
     let mut output = OutputBuilder::new("Configuration");
 
-    for (game, config) in &config.options.game {
-        output.section(game.title(), |builder| {
+    let games = config.games()?;
+
+    for (name, config) in &config.options.game {
+        let title = games.find(name).map_or(name.as_str(), |game| &game.title);
+
+        output.section(title, |builder| {
             if let Some(boot_boost) = config.boot_boost {
                 builder.property("Boot boost", boot_boost);
             }
@@ -69,6 +74,30 @@ pub fn info(config: Config) -> color_eyre::Result<()> {
         }
     });
 
+    output.section("Installed games", |builder| {
+        for game in games.iter() {
+            let Ok(exe) = game_exe_path(&config, game, &config.game_options(game)) else {
+                continue;
+            };
+
+            let Ok(fingerprint) = Fingerprint::read(&exe) else {
+                continue;
+            };
+
+            let version = ExecutableVersion::read(&exe)
+                .map_or_else(|_| "unknown version".to_owned(), |v| v.product.to_string());
+
+            let build = fingerprint
+                .identify()
+                .map_or_else(|| "unknown build".yellow().to_string(), |b| b.to_string());
+
+            builder.property(
+                &game.title,
+                format!("{version}, {build} (fingerprint: {fingerprint})"),
+            );
+        }
+    });
+
     print!("{}", output.build());
 
     Ok(())
@@ -19,20 +19,32 @@ use clap::{
     ArgAction, Args,
 };
 use color_eyre::eyre::{eyre, OptionExt};
-use me3_env::{CommandExt, LauncherVars, TelemetryVars};
+use me3_binary_analysis::{
+    fingerprint::Fingerprint,
+    version::{ExecutableVersion, Version},
+};
+use me3_env::{CommandExt, GameVars, LauncherVars, TelemetryVars};
 use me3_launcher_attach_protocol::AttachConfig;
-use me3_mod_protocol::{native::Native, package::Package};
+use me3_mod_protocol::{
+    game::GameDefinition, native::Native, package::Package, validation::check_savefile_name,
+    variables::PathVariables,
+};
 use normpath::PathExt;
 use serde::{Deserialize, Serialize};
 use steamlocate::{Library, SteamDir};
 use tempfile::NamedTempFile;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::{
-    commands::{launch::proton::CompatTools, profile::ProfileOptions},
+    commands::{
+        launch::proton::CompatTools,
+        profile::{print_lock_diff, ProfileOptions},
+    },
     config::Config,
-    db::{profile::Profile, DbContext},
-    Game,
+    db::{
+        profile::{lock::ProfileLock, Profile},
+        DbContext,
+    },
 };
 
 fn remap_slr_path(path: impl AsRef<Path>) -> PathBuf {
@@ -58,16 +70,10 @@ pub struct Selector {
     #[clap(long, help_heading = "Game selection", action = ArgAction::SetTrue, required = false)]
     auto_detect: bool,
 
-    /// Short name of a game to launch.
-    #[clap(
-        short('g'),
-        long,
-        hide_possible_values = false,
-        help_heading = "Game selection",
-        required = false
-    )]
-    #[arg(value_enum)]
-    game: Option<Game>,
+    /// Short name of a game to launch, e.g. `eldenring`, or the name of a game defined in
+    /// me3.toml.
+    #[clap(short('g'), long, help_heading = "Game selection", required = false)]
+    game: Option<String>,
 
     /// Steam APPID of the game to launch.
     #[clap(
@@ -137,6 +143,20 @@ pub struct LaunchArgs {
     #[clap(long("suspend"), action = ArgAction::SetTrue)]
     suspend: bool,
 
+    /// Scan packages for changes instead of reusing the results from earlier launches.
+    #[clap(long("rescan-packages"), action = ArgAction::SetTrue)]
+    rescan_packages: bool,
+
+    /// Refuse to launch unless the profile's natives and package files match its lockfile.
+    ///
+    /// Without this option, differences from the lockfile are only reported as a warning.
+    #[clap(long("locked"), action = ArgAction::SetTrue)]
+    locked: bool,
+
+    /// Launch even if the game is older than the version the profile requires.
+    #[clap(long("ignore-game-version"), action = ArgAction::SetTrue)]
+    ignore_game_version: bool,
+
     /// Name of a profile in the me3 profile dir, or path to a ModProfile (TOML or JSON).
     #[arg(
             short('p'),
@@ -249,9 +269,9 @@ impl Launcher for CompatToolLauncher {
 }
 
 struct LaunchContext {
-    game: Game,
+    game: GameDefinition,
+    game_exe_path: PathBuf,
     profile: Profile,
-    game_options: GameOptions,
     profile_options: ProfileOptions,
     attach_config: AttachConfig,
 }
@@ -274,53 +294,162 @@ impl LaunchArgs {
             steam_id: None,
         });
 
+        let games = config.games()?;
+
         let game = if target_selector.auto_detect {
             profile
                 .supported_game()
-                .map(crate::Game)
+                .and_then(|engine| games.for_engine(engine))
                 .ok_or_eyre("unable to determine which game to launch")
         } else {
             target_selector
                 .game
-                .or_else(|| target_selector.steam_id.and_then(Game::from_app_id))
+                .as_deref()
+                .and_then(|name| games.find(name))
+                .or_else(|| {
+                    target_selector
+                        .steam_id
+                        .and_then(|app_id| games.from_app_id(app_id))
+                })
                 .ok_or_eyre("unable to determine game from name or app ID")
-        }?;
+        }?
+        .clone();
 
-        let game_options = config
-            .options
-            .game
-            .get(&game.0)
-            .cloned()
-            .unwrap_or_default()
-            .merge(self.game_options.clone());
+        let profile = profile.for_game(game.engine);
+
+        let game_options = config.game_options(&game).merge(self.game_options.clone());
 
         let profile_options = profile.options().merge(self.profile_options.clone());
 
         info!(?game, ?game_options, ?profile_options, "resolved game");
 
-        let attach_config = self.generate_attach_config(
-            game,
+        let game_exe_path = game_exe_path(config, &game, &game_options)?;
+        self.check_game_version(&game, &profile, &game_exe_path)?;
+
+        let variables = config.path_variables(game_exe_path.parent());
+
+        let mut attach_config = self.generate_attach_config(
+            &game,
             &game_options,
             &profile,
             &profile_options,
+            &variables,
             config.cache_dir(),
         )?;
 
+        if let Some(fingerprint) = fingerprint_game(&game_exe_path) {
+            let build = fingerprint.identify().map(|build| build.to_string());
+
+            info!(%fingerprint, ?build, "identified game build");
+
+            me3_telemetry::set_tag("game.fingerprint", &fingerprint.to_string());
+            me3_telemetry::set_tag("game.build", build.as_deref().unwrap_or("unknown"));
+
+            attach_config.game_fingerprint = Some(fingerprint.to_string());
+            attach_config.game_build = build;
+        }
+
         Ok(LaunchContext {
             game,
+            game_exe_path,
             profile,
-            game_options,
             profile_options,
             attach_config,
         })
     }
 
+    /// Compares the version of the game executable to the oldest version the profile supports, if
+    /// it sets one.
+    fn check_game_version(
+        &self,
+        game: &GameDefinition,
+        profile: &Profile,
+        game_exe_path: &Path,
+    ) -> color_eyre::Result<()> {
+        let Some(required) = profile.required_version(game.engine)? else {
+            return Ok(());
+        };
+
+        let installed = match ExecutableVersion::read(game_exe_path) {
+            Ok(version) => version.product,
+            Err(e) => {
+                warn!(
+                    error = &e as &dyn Error,
+                    path = %game_exe_path.display(),
+                    "unable to read the game version, skipping the version check"
+                );
+
+                return Ok(());
+            }
+        };
+
+        if installed >= required {
+            info!(%installed, %required, "game version is supported");
+            return Ok(());
+        }
+
+        if self.ignore_game_version {
+            warn!(%installed, %required, "the game is older than the profile supports");
+            return Ok(());
+        }
+
+        Err(eyre!(
+            "{} requires {} version {required} or newer, but version {installed} is installed \
+             (use --ignore-game-version to launch it anyway)",
+            profile.name(),
+            game.title,
+        ))
+    }
+
+    /// Compares the natives and packages of the profile to its lockfile, if it has one.
+    fn check_lock(
+        &self,
+        game: &GameDefinition,
+        profile: &Profile,
+        natives: &[Native],
+        packages: &[Package],
+    ) -> color_eyre::Result<()> {
+        let lock_path = profile.lock_path();
+
+        let Some(lock) = ProfileLock::load(&lock_path)? else {
+            if self.locked {
+                return Err(eyre!(
+                    "{} has no lockfile, create one with `me3 profile lock`",
+                    profile.name()
+                ));
+            }
+
+            return Ok(());
+        };
+
+        let diff = lock.diff(&ProfileLock::create(Some(game.engine), natives, packages)?);
+
+        if diff.is_empty() {
+            info!(path = %lock_path.display(), "all files match the lockfile");
+            return Ok(());
+        }
+
+        print_lock_diff(&lock_path, &diff);
+
+        if self.locked {
+            return Err(eyre!(
+                "the natives or packages of {} differ from its lockfile",
+                profile.name()
+            ));
+        }
+
+        warn!(path = %lock_path.display(), "the natives or packages differ from the lockfile");
+
+        Ok(())
+    }
+
     fn generate_attach_config(
         &self,
-        game: Game,
+        game: &GameDefinition,
         opts: &GameOptions,
         profile: &Profile,
         profile_options: &ProfileOptions,
+        variables: &PathVariables,
         cache_path: Option<Box<Path>>,
     ) -> color_eyre::Result<AttachConfig> {
         for path in self.natives.iter().chain(&self.packages) {
@@ -343,7 +472,11 @@ impl LaunchArgs {
             .map(|normalized| Native::new(normalized.into_path_buf()))
             .collect::<Vec<_>>();
 
-        let (ordered_natives, ordered_packages) = profile.compile()?;
+        let (ordered_natives, ordered_packages) = profile.compile(variables)?;
+
+        if self.profile.is_some() {
+            self.check_lock(game, profile, &ordered_natives, &ordered_packages)?;
+        }
 
         packages.extend(ordered_packages);
         natives.extend(ordered_natives);
@@ -351,27 +484,19 @@ impl LaunchArgs {
         let savefile = self.savefile.clone().or_else(|| profile.savefile());
 
         if let Some(savefile) = &savefile {
-            // https://learn.microsoft.com/en-us/windows/win32/fileio/naming-a-file#naming-conventions
-            let is_windows_path_reserved_char = |c: char| {
-                matches!(
-                    c,
-                    '\x00'..'\x1f' | '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*'
-                )
-            };
-
-            if savefile.chars().any(is_windows_path_reserved_char) {
-                return Err(eyre!(
-                    "savefile name ({savefile:?}) contains reserved file name characters"
-                ));
-            }
+            check_savefile_name(savefile)?;
         }
 
         Ok(AttachConfig {
-            game: game.into(),
+            game: game.engine,
             packages,
             natives,
             savefile,
+            savefile_dir: game.savefile_dir.clone(),
+            game_fingerprint: None,
+            game_build: None,
             cache_path: cache_path.map(|path| path.into_path_buf()),
+            rescan_packages: self.rescan_packages,
             suspend: self.suspend,
             boot_boost: opts.boot_boost.unwrap_or(true),
             skip_logos: opts.skip_logos.unwrap_or(true),
@@ -383,12 +508,50 @@ impl LaunchArgs {
     }
 }
 
+/// Finds the executable to launch `game` with, using Steam unless it is configured explicitly.
+pub(crate) fn game_exe_path(
+    config: &Config,
+    game: &GameDefinition,
+    game_options: &GameOptions,
+) -> color_eyre::Result<PathBuf> {
+    if let Some(exe) = &game_options.exe {
+        return Ok(exe.clone());
+    }
+
+    let steam_dir = config.steam_dir()?;
+    let (app, library) = steam_dir.find_app(game.app_id)?.ok_or_eyre(
+        "Steam was used to locate the game executable and no game installation was found",
+    )?;
+
+    Ok(library.resolve_app_dir(&app).join(&game.executable))
+}
+
+/// The version of the installed game, if it can be found and has a version resource.
+pub(crate) fn installed_version(config: &Config, game: &GameDefinition) -> Option<Version> {
+    let exe = game_exe_path(config, game, &config.game_options(game)).ok()?;
+    let version = ExecutableVersion::read(&exe).ok()?;
+
+    Some(version.product)
+}
+
+fn fingerprint_game(game_exe_path: &Path) -> Option<Fingerprint> {
+    Fingerprint::read(game_exe_path)
+        .inspect_err(|e| {
+            warn!(
+                error = e as &dyn Error,
+                path = %game_exe_path.display(),
+                "unable to fingerprint the game executable"
+            )
+        })
+        .ok()
+}
+
 #[tracing::instrument(err, skip_all)]
 pub fn launch(db: DbContext, config: Config, args: LaunchArgs) -> color_eyre::Result<()> {
     let LaunchContext {
         game,
+        game_exe_path,
         profile,
-        game_options,
         profile_options: _profile_options,
         attach_config,
     } = args.parse_with_context(&db, &config)?;
@@ -397,7 +560,7 @@ pub fn launch(db: DbContext, config: Config, args: LaunchArgs) -> color_eyre::Re
         .windows_binaries_dir()
         .ok_or_eyre("Can't find location of windows-binaries-dir")?;
 
-    let app_id = game.app_id();
+    let app_id = game.app_id;
     let launcher_path = if cfg!(target_os = "linux") {
         remap_slr_path(bins_dir.join("me3-launcher.exe"))
     } else {
@@ -410,20 +573,6 @@ pub fn launch(db: DbContext, config: Config, args: LaunchArgs) -> color_eyre::Re
         bins_dir.join("me3_mod_host.dll")
     };
 
-    let game_exe_path = game_options
-        .exe
-        .map(color_eyre::eyre::Ok)
-        .unwrap_or_else(|| {
-            let steam_dir = config.steam_dir()?;
-            let (app, library) = steam_dir.find_app(app_id)?.ok_or_eyre(
-                "Steam was used to locate the game executable and no game installation was found",
-            )?;
-
-            let game_exe = library.resolve_app_dir(&app).join(game.launcher());
-
-            Ok(game_exe)
-        })?;
-
     let mut injector_command = if cfg!(target_os = "linux") {
         let steam_dir = config.steam_dir()?;
         let (_, steam_library) = steam_dir.find_app(app_id)?.ok_or_eyre(
@@ -439,7 +588,7 @@ pub fn launch(db: DbContext, config: Config, args: LaunchArgs) -> color_eyre::Re
 
         let compat_tool_name = app_compat_tool
             .and_then(|tool| tool.name.clone())
-            .or_else(|| game.0.verified_on_deck_runtime().map(|rt| rt.to_string()))
+            .or_else(|| game.deck_runtime.clone())
             .ok_or_eyre("unable to determine Proton runtime to run game with")?;
 
         let compat_tool = compat_tools.find(&compat_tool_name).ok_or_eyre(format!(
@@ -488,7 +637,9 @@ pub fn launch(db: DbContext, config: Config, args: LaunchArgs) -> color_eyre::Re
     };
 
     injector_command
-        .with_env_vars(game.into_vars())
+        .with_env_vars(GameVars {
+            launched: game.engine,
+        })
         .with_env_vars(launcher_vars)
         .with_env_vars(telemetry_vars)
         .env("SteamAppId", app_id.to_string())
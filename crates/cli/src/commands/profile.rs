@@ -1,16 +1,37 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
-use clap::{ArgAction, Args, Subcommand};
+use clap::{ArgAction, Args, Subcommand, ValueEnum};
 use color_eyre::eyre::{eyre, OptionExt};
+use me3_mod_host_assets::mapping::VfsOverrideMapping;
 use me3_mod_protocol::{
     dependency::Dependency,
+    game::{GameDefinition, GameDefinitions},
+    graph::DependencyGraph,
+    metadata::Metadata,
     native::Native,
     package::{Package, WithPackageSource},
+    validation::{validate_file, Diagnostic, Severity, Validation},
+    variables::PathVariables,
     ModProfile, Supports,
 };
-use tracing::error;
-
-use crate::{config::Config, db::DbContext, output::OutputBuilder, Game};
+use tracing::{error, info, warn};
+
+use crate::{
+    commands::launch::{game_exe_path, installed_version},
+    config::Config,
+    db::{
+        profile::{
+            lock::{LockDiff, ProfileLock},
+            Profile,
+        },
+        DbContext,
+    },
+    output::OutputBuilder,
+    Game,
+};
 
 #[derive(Subcommand, Debug)]
 #[command(flatten_help = true)]
@@ -24,6 +45,24 @@ pub enum ProfileCommands {
 
     /// Show information on a profile.
     Show(#[clap(flatten)] ProfileNameArgs),
+
+    /// Rewrite a profile in place using the latest profile format version.
+    Upgrade(#[clap(flatten)] ProfileNameArgs),
+
+    /// Check a profile for problems, exiting with an error if any are found.
+    Validate(#[clap(flatten)] ProfileNameArgs),
+
+    /// List assets that are provided by more than one package and which package wins.
+    Conflicts(ProfileConflictsArgs),
+
+    /// Render the load order of natives and packages and the relations between them as a graph.
+    Graph(ProfileGraphArgs),
+
+    /// Record the contents of every native and package file in a lockfile next to the profile.
+    Lock(ProfileLockArgs),
+
+    /// Compare the natives and package files of a profile to its lockfile.
+    Verify(ProfileLockArgs),
 }
 
 #[derive(Args, Debug)]
@@ -61,6 +100,57 @@ pub struct ProfileCreateArgs {
     overwrite: bool,
 }
 
+#[derive(Args, Debug)]
+pub struct ProfileConflictsArgs {
+    #[clap(flatten)]
+    name: ProfileNameArgs,
+
+    /// Game to include the game specific packages of, by short name, e.g. `eldenring`, or the
+    /// name of a game defined in me3.toml.
+    #[clap(short('g'), long, help_heading = "Game selection")]
+    game: Option<String>,
+
+    /// Exit with an error if a conflict is only decided by the order packages are listed in,
+    /// rather than by `load_before` or `load_after`.
+    #[clap(long, action = ArgAction::SetTrue)]
+    deny_unresolved: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ProfileGraphArgs {
+    #[clap(flatten)]
+    name: ProfileNameArgs,
+
+    /// Game to include the game specific natives and packages of, by short name, e.g. `eldenring`,
+    /// or the name of a game defined in me3.toml.
+    #[clap(short('g'), long, help_heading = "Game selection")]
+    game: Option<String>,
+
+    /// Language to render the graph in.
+    #[clap(long, value_enum, default_value_t = GraphFormat::Dot)]
+    format: GraphFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct ProfileLockArgs {
+    #[clap(flatten)]
+    name: ProfileNameArgs,
+
+    /// Game to include the game specific natives and packages of, by short name, e.g. `eldenring`,
+    /// or the name of a game defined in me3.toml.
+    #[clap(short('g'), long, help_heading = "Game selection")]
+    game: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz DOT.
+    Dot,
+
+    /// Mermaid flowchart.
+    Mermaid,
+}
+
 #[derive(Args, Clone, Debug, Default, PartialEq)]
 pub struct ProfileOptions {
     /// Re-enable online matchmaking? (ban risk)
@@ -190,6 +280,7 @@ pub fn show(db: DbContext, config: Config, name: ProfileNameArgs) -> color_eyre:
     let mut output = OutputBuilder::new("Mod Profile");
 
     output.property("Name", profile.name());
+    output.property("Version", profile.version());
 
     output.property(
         "Path",
@@ -199,21 +290,74 @@ pub fn show(db: DbContext, config: Config, name: ProfileNameArgs) -> color_eyre:
         },
     );
 
+    if !profile.extends().is_empty() {
+        output.section("Extends", |builder| {
+            for (index, extended) in profile.extends().iter().enumerate() {
+                builder.property(format!("{}", index + 1), extended.to_string_lossy());
+            }
+        });
+    }
+
+    let games = config.games()?;
+
     output.section("Supports", |builder| {
-        if let Some(game) = profile.supported_game() {
-            builder.property(format!("{game:?}"), "Supported");
+        for support in profile.supports() {
+            let game = games
+                .for_engine(support.game)
+                .unwrap_or_else(|| support.game.definition());
+
+            let requirement = match &support.since_version {
+                Some(since) => format!("{since} or newer"),
+                None => "Supported".to_owned(),
+            };
+
+            let installed = match installed_version(&config, game) {
+                Some(version) => format!("installed: {version}"),
+                None => "installed version unknown".to_owned(),
+            };
+
+            builder.property(&game.title, format!("{requirement} ({installed})"));
         }
     });
 
+    let variables = path_variables(&config, selected_game(&games, None, &profile)?);
+
     output.section("Natives", |builder| {
         for native in profile.natives() {
-            builder.section(native.id(), |builder| {
-                builder.indent(2);
-
-                builder.property("Path", native.source().to_string_lossy());
-                builder.property("Optional", native.optional.to_string());
-                builder.property("Enabled", native.enabled);
-            });
+            let mut expanded = profile
+                .expand_native(native.clone(), &variables)
+                .unwrap_or_else(|e| {
+                    warn!(error = ?e, "failed to resolve native path");
+                    vec![]
+                });
+
+            // Natives that point to a glob pattern or directory are shown for each DLL they match.
+            let pattern = match &expanded[..] {
+                [] => None,
+                [single] if single.id() == native.id() => None,
+                _ => Some(native.source()),
+            };
+
+            if pattern.is_none() {
+                expanded = vec![native.clone()];
+            }
+
+            for entry in expanded {
+                builder.section(entry.id(), |builder| {
+                    builder.indent(2);
+
+                    builder.property("Path", entry.source().to_string_lossy());
+                    if let Some(pattern) = pattern {
+                        builder.property("Matched by", pattern.to_string_lossy());
+                    }
+                    if let Some(source) = profile.native_source(&native) {
+                        builder.property("Source", source.to_string_lossy());
+                    }
+                    metadata_properties(builder, &entry.metadata);
+                    builder.property("Optional", entry.optional.to_string());
+                    builder.property("Enabled", entry.enabled);
+                });
+            }
         }
     });
 
@@ -222,11 +366,37 @@ pub fn show(db: DbContext, config: Config, name: ProfileNameArgs) -> color_eyre:
             builder.section(package.id(), |builder| {
                 builder.indent(2);
                 builder.property("Path", package.source().to_string_lossy());
+                if let Some(source) = profile.package_source(&package) {
+                    builder.property("Source", source.to_string_lossy());
+                }
+
+                let metadata = profile.package_metadata(&package).unwrap_or_else(|e| {
+                    warn!(error = ?e, "failed to read package metadata");
+                    package.metadata.clone()
+                });
+                metadata_properties(builder, &metadata);
                 builder.property("Enabled", package.enabled);
             });
         }
     });
 
+    let files = profile.files().collect::<Vec<_>>();
+    if !files.is_empty() {
+        output.section("Files", |builder| {
+            for file in files {
+                builder.section(file.id(), |builder| {
+                    builder.indent(2);
+                    builder.property("Path", file.source().to_string_lossy());
+                    builder.property("Target", file.target());
+                    if let Some(source) = profile.file_source(&file) {
+                        builder.property("Source", source.to_string_lossy());
+                    }
+                    builder.property("Enabled", file.enabled);
+                });
+            }
+        });
+    }
+
     if let Some(savefile) = profile.savefile() {
         output.property("Savefile", savefile);
     }
@@ -245,6 +415,228 @@ pub fn show(db: DbContext, config: Config, name: ProfileNameArgs) -> color_eyre:
     Ok(())
 }
 
+fn metadata_properties(builder: &mut OutputBuilder, metadata: &Metadata) {
+    if let Some(name) = &metadata.name {
+        builder.property("Name", name);
+    }
+    if let Some(version) = &metadata.version {
+        builder.property("Version", version);
+    }
+    if !metadata.authors.is_empty() {
+        builder.property("Authors", metadata.authors.join(", "));
+    }
+    if let Some(homepage) = &metadata.homepage {
+        builder.property("Homepage", homepage);
+    }
+}
+
+#[tracing::instrument(err, skip_all)]
+pub fn upgrade(db: DbContext, config: Config, name: ProfileNameArgs) -> color_eyre::Result<()> {
+    let profile_path = name.into_profile_path(&config)?;
+
+    let mut profile = db.profiles.load(profile_path)?;
+
+    if !profile.upgrade() {
+        info!(path = %profile.path().display(), "profile is already up to date");
+        return Ok(());
+    }
+
+    profile.save()?;
+
+    info!(path = %profile.path().display(), version = profile.version(), "upgraded profile");
+
+    Ok(())
+}
+
+#[tracing::instrument(err, skip_all)]
+pub fn validate(db: DbContext, config: Config, name: ProfileNameArgs) -> color_eyre::Result<()> {
+    let profile_path = name.into_profile_path(&config)?;
+    let profile_path = db.profiles.find(&profile_path)?;
+
+    let profile = db.profiles.load(&profile_path);
+    let validation = validate_file(
+        &profile_path,
+        profile.as_ref().ok().and_then(|p| p.inherited()),
+    )?;
+
+    for diagnostic in validation.diagnostics() {
+        println!("{}", render_diagnostic(&validation, diagnostic));
+    }
+
+    let mut errors = validation
+        .diagnostics()
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+
+    // Problems with the profiles this profile extends aren't part of this profile's source.
+    if let (false, Err(e)) = (validation.has_errors(), &profile) {
+        println!("{}: {e:#}\n", Severity::Error);
+        errors += 1;
+    }
+
+    if errors > 0 {
+        return Err(eyre!(
+            "{} has {errors} error(s)",
+            validation.path().display()
+        ));
+    }
+
+    info!(path = %validation.path().display(), "no errors found");
+
+    Ok(())
+}
+
+/// The game selected with `--game`, resolved through the configured games like `me3 launch`
+/// does, or else the game the profile supports.
+fn selected_game<'a>(
+    games: &'a GameDefinitions,
+    name: Option<&str>,
+    profile: &Profile,
+) -> color_eyre::Result<Option<&'a GameDefinition>> {
+    match name {
+        Some(name) => games
+            .find(name)
+            .map(Some)
+            .ok_or_else(|| eyre!("unknown game {name:?}")),
+        None => Ok(profile.supported_game().map(|engine| {
+            games
+                .for_engine(engine)
+                .unwrap_or_else(|| engine.definition())
+        })),
+    }
+}
+
+/// The variables used to compile a profile for `game` without launching it. `${GAME_DIR}` is
+/// left undefined if the game can't be found.
+fn path_variables(config: &Config, game: Option<&GameDefinition>) -> PathVariables {
+    let game_exe =
+        game.and_then(|game| game_exe_path(config, game, &config.game_options(game)).ok());
+
+    config.path_variables(game_exe.as_deref().and_then(Path::parent))
+}
+
+#[tracing::instrument(err, skip_all)]
+pub fn conflicts(
+    db: DbContext,
+    config: Config,
+    args: ProfileConflictsArgs,
+) -> color_eyre::Result<()> {
+    let profile_path = args.name.into_profile_path(&config)?;
+
+    let mut profile = db.profiles.load(profile_path)?;
+    let games = config.games()?;
+    let game = selected_game(&games, args.game.as_deref(), &profile)?;
+    if let Some(game) = game {
+        profile = profile.for_game(game.engine);
+    }
+
+    let (_, packages) = profile.compile(&path_variables(&config, game))?;
+
+    let mut mapping = VfsOverrideMapping::new()?;
+    mapping.scan_directories(packages.iter())?;
+
+    let conflicts = mapping.conflict_report(&packages);
+
+    let mut output = OutputBuilder::new("Asset Conflicts");
+
+    for conflict in &conflicts {
+        output.section(conflict.path.to_string_lossy(), |builder| {
+            builder.indent(2);
+            builder.property("Providers", conflict.providers.join(", "));
+            builder.property("Winner", conflict.winner());
+            builder.property(
+                "Decided by",
+                if conflict.resolved {
+                    "load_before/load_after"
+                } else {
+                    "profile order"
+                },
+            );
+        });
+    }
+
+    println!("{}", output.build());
+
+    let unresolved = conflicts
+        .iter()
+        .filter(|conflict| !conflict.resolved)
+        .count();
+
+    if args.deny_unresolved && unresolved > 0 {
+        return Err(eyre!(
+            "{unresolved} conflict(s) are not decided by load_before/load_after"
+        ));
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(err, skip_all)]
+pub fn graph(db: DbContext, config: Config, args: ProfileGraphArgs) -> color_eyre::Result<()> {
+    let profile_path = args.name.into_profile_path(&config)?;
+
+    let mut profile = db.profiles.load(profile_path)?;
+    let games = config.games()?;
+    let game = selected_game(&games, args.game.as_deref(), &profile)?;
+    if let Some(game) = game {
+        profile = profile.for_game(game.engine);
+    }
+
+    let (natives, packages) = profile.compile(&path_variables(&config, game))?;
+
+    let mut graph = DependencyGraph::new();
+    graph.add_group("Natives", &natives);
+    graph.add_group("Packages", &packages);
+
+    match args.format {
+        GraphFormat::Dot => print!("{}", graph.to_dot()),
+        GraphFormat::Mermaid => print!("{}", graph.to_mermaid()),
+    }
+
+    Ok(())
+}
+
+/// Render a diagnostic along with the line of the profile it refers to.
+fn render_diagnostic(validation: &Validation, diagnostic: &Diagnostic) -> String {
+    let mut output = format!("{}: {}\n", diagnostic.severity, diagnostic.message);
+
+    let Some(span) = &diagnostic.span else {
+        output.push_str(&format!(" --> {}\n", validation.path().display()));
+        return output;
+    };
+
+    let location = validation.location(span.start);
+    let line = validation.line(location.line).unwrap_or_default();
+    let gutter = " ".repeat(location.line.to_string().len());
+
+    let source = validation.source();
+    let line_end = source[..span.start]
+        .rfind('\n')
+        .map_or(0, |index| index + 1)
+        + line.len();
+    let underline = source[span.start..span.end.clamp(span.start, line_end)]
+        .chars()
+        .count()
+        .max(1);
+
+    output.push_str(&format!(
+        "{gutter}--> {}:{}:{}\n",
+        validation.path().display(),
+        location.line,
+        location.column
+    ));
+    output.push_str(&format!("{gutter} |\n"));
+    output.push_str(&format!("{} | {line}\n", location.line));
+    output.push_str(&format!(
+        "{gutter} | {}{}\n",
+        " ".repeat(location.column - 1),
+        "^".repeat(underline)
+    ));
+
+    output
+}
+
 pub fn no_profile_dir() -> color_eyre::Report {
     eyre!(
         r#"No profile directory was configured and the default profile directory was inaccessible.
@@ -254,3 +646,94 @@ pub fn no_profile_dir() -> color_eyre::Report {
     "#
     )
 }
+
+/// Hashes the natives and package files a profile loads for `game`.
+pub(crate) fn hash_profile(
+    profile: &Profile,
+    game: Option<me3_mod_protocol::Game>,
+    variables: &PathVariables,
+) -> color_eyre::Result<ProfileLock> {
+    let (natives, packages) = profile.compile(variables)?;
+    ProfileLock::create(game, &natives, &packages)
+}
+
+/// Prints the differences between a lockfile and the files that are loaded.
+pub(crate) fn print_lock_diff(lock_path: &Path, diff: &LockDiff) {
+    if let Some(game) = diff.game {
+        println!("{} was created for {game}", lock_path.display());
+    }
+
+    for (prefix, files) in [
+        ("~", &diff.changed),
+        ("+", &diff.added),
+        ("-", &diff.removed),
+    ] {
+        for file in files {
+            println!("{prefix} {file}");
+        }
+    }
+}
+
+#[tracing::instrument(err, skip_all)]
+pub fn lock(db: DbContext, config: Config, args: ProfileLockArgs) -> color_eyre::Result<()> {
+    let profile_path = args.name.into_profile_path(&config)?;
+
+    let mut profile = db.profiles.load(profile_path)?;
+    let games = config.games()?;
+    let game = selected_game(&games, args.game.as_deref(), &profile)?;
+    if let Some(game) = game {
+        profile = profile.for_game(game.engine);
+    }
+
+    let lock = hash_profile(
+        &profile,
+        game.map(|game| game.engine),
+        &path_variables(&config, game),
+    )?;
+    let lock_path = profile.lock_path();
+    lock.save(&lock_path)?;
+
+    info!(path = %lock_path.display(), files = lock.file_count(), "wrote lockfile");
+
+    Ok(())
+}
+
+#[tracing::instrument(err, skip_all)]
+pub fn verify(db: DbContext, config: Config, args: ProfileLockArgs) -> color_eyre::Result<()> {
+    let profile_path = args.name.into_profile_path(&config)?;
+
+    let mut profile = db.profiles.load(profile_path)?;
+    let games = config.games()?;
+    let game = selected_game(&games, args.game.as_deref(), &profile)?;
+    if let Some(game) = game {
+        profile = profile.for_game(game.engine);
+    }
+
+    let lock_path = profile.lock_path();
+    let lock = ProfileLock::load(&lock_path)?.ok_or_else(|| {
+        eyre!(
+            "{} has no lockfile, create one with `me3 profile lock`",
+            profile.name()
+        )
+    })?;
+
+    let diff = lock.diff(&hash_profile(
+        &profile,
+        game.map(|game| game.engine),
+        &path_variables(&config, game),
+    )?);
+
+    print_lock_diff(&lock_path, &diff);
+
+    if !diff.is_empty() {
+        return Err(eyre!(
+            "{} file(s) differ from {}",
+            diff.changed.len() + diff.added.len() + diff.removed.len(),
+            lock_path.display()
+        ));
+    }
+
+    info!(path = %lock_path.display(), "all files match the lockfile");
+
+    Ok(())
+}
@@ -5,7 +5,10 @@ use std::{
 };
 
 use color_eyre::Result;
-use me3_mod_protocol::Game;
+use me3_mod_protocol::{
+    game::{GameDefinition, GameDefinitions, GameEntry},
+    variables::{PathVariables, GAME_DIR, ME3_DATA_DIR},
+};
 use serde::{Deserialize, Serialize};
 use steamlocate::SteamDir;
 use tracing::error;
@@ -38,9 +41,15 @@ pub struct Options {
     #[clap(long, help_heading = "Configuration", value_hint = clap::ValueHint::DirPath)]
     pub(crate) windows_binaries_dir: Option<Box<Path>>,
 
+    /// Options for a game, keyed by any of its names.
+    #[clap(skip)]
+    #[serde(default)]
+    pub(crate) game: BTreeMap<String, GameOptions>,
+
+    /// Games to add to the built-in game table, or fields to override in it.
     #[clap(skip)]
     #[serde(default)]
-    pub(crate) game: BTreeMap<Game, GameOptions>,
+    pub(crate) games: BTreeMap<String, GameEntry>,
 }
 
 pub struct Config {
@@ -82,6 +91,54 @@ impl Config {
             .unwrap_or_else(SteamDir::locate)?)
     }
 
+    /// The built-in games, extended by the `[games.<name>]` tables of the configuration.
+    pub fn games(&self) -> Result<GameDefinitions> {
+        Ok(GameDefinitions::with_entries(
+            self.options
+                .games
+                .iter()
+                .map(|(name, entry)| (name.as_str(), entry)),
+        )?)
+    }
+
+    /// The options configured for `game` under any of its names. Options set under the game's
+    /// primary name take precedence over the ones set under its aliases.
+    pub fn game_options(&self, game: &GameDefinition) -> GameOptions {
+        let (primary, aliases): (Vec<_>, Vec<_>) = self
+            .options
+            .game
+            .iter()
+            .filter(|(name, _)| game.is_named(name))
+            .partition(|(name, _)| name.eq_ignore_ascii_case(&game.name));
+
+        aliases
+            .into_iter()
+            .chain(primary)
+            .fold(GameOptions::default(), |options, (_, other)| {
+                options.merge(other.clone())
+            })
+    }
+
+    /// The variables that can be used in the paths of a profile's natives, packages and files.
+    /// `${GAME_DIR}` is only defined when the game's location is known.
+    pub fn path_variables(&self, game_dir: Option<&Path>) -> PathVariables {
+        let mut variables = PathVariables::new();
+
+        if let Some(game_dir) = game_dir {
+            variables.set(GAME_DIR, game_dir);
+        }
+
+        if let Some(data_dir) = self.known_dirs.data_dir() {
+            variables.set(ME3_DATA_DIR, data_dir);
+        }
+
+        if let Some(home_dir) = self.known_dirs.home_dir() {
+            variables.set_home(home_dir);
+        }
+
+        variables
+    }
+
     pub fn resolve_profile(&self, profile_name: &str) -> Result<PathBuf> {
         if let Ok(true) = std::fs::exists(profile_name) {
             Ok(PathBuf::from(profile_name))
@@ -99,6 +156,7 @@ impl Options {
         Self {
             crash_reporting: other.crash_reporting.or(self.crash_reporting),
             game: other.game.into_iter().chain(self.game).collect(),
+            games: self.games.into_iter().chain(other.games).collect(),
             profile_dir: other.profile_dir.or(self.profile_dir),
             steam_dir: other.steam_dir.or(self.steam_dir),
             windows_binaries_dir: other.windows_binaries_dir.or(self.windows_binaries_dir),
@@ -129,3 +187,34 @@ impl Options {
         Ok(config)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use me3_mod_protocol::Game;
+
+    use super::{Config, KnownDirs, Options};
+
+    #[test]
+    fn primary_game_name_takes_precedence() {
+        let options = toml::from_str::<Options>(
+            r#"
+            [game.eldenring]
+            skip_logos = true
+
+            [game.er]
+            skip_logos = false
+            boot_boost = false
+            "#,
+        )
+        .unwrap();
+
+        let config = Config {
+            options,
+            known_dirs: KnownDirs::default(),
+        };
+
+        let options = config.game_options(Game::EldenRing.definition());
+        assert_eq!(options.skip_logos, Some(true));
+        assert_eq!(options.boot_boost, Some(false));
+    }
+}
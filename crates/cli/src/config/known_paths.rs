@@ -1,7 +1,7 @@
 use std::path::{Path, PathBuf};
 
 use color_eyre::Result;
-use directories::ProjectDirs;
+use directories::{BaseDirs, ProjectDirs};
 
 #[derive(Debug)]
 pub struct KnownDirs {
@@ -100,6 +100,11 @@ impl KnownDirs {
             .or(self.cwd.clone())
     }
 
+    /// Discover the home directory of the current user.
+    pub fn home_dir(&self) -> Option<Box<Path>> {
+        BaseDirs::new().map(|dirs| Box::from(dirs.home_dir()))
+    }
+
     /// Discover candidate paths to me3 Windows binary directories, ordered from least priority to
     /// highest.
     ///
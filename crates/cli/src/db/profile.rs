@@ -1,21 +1,30 @@
 use std::{
+    collections::HashMap,
     ffi::OsStr,
     fs::DirEntry,
     path::{Path, PathBuf},
 };
 
 use color_eyre::eyre::Context;
+use me3_binary_analysis::version::Version;
 use me3_mod_protocol::{
-    dependency::sort_dependencies,
+    dependency::Dependency,
+    file::FileMapping,
+    load_order::sort_load_order,
+    metadata::Metadata,
     native::Native,
     package::{Package, WithPackageSource},
-    Game, ModProfile,
+    variables::{PathVariables, PROFILE_DIR},
+    Game, ModProfile, Supports,
 };
 use normpath::PathExt;
 use tracing::warn;
 
 use crate::commands::profile::ProfileOptions;
 
+mod glob;
+pub mod lock;
+
 pub struct ProfileDb {
     search_paths: Vec<Box<Path>>,
 }
@@ -31,7 +40,18 @@ impl ProfileDb {
 pub struct Profile {
     name: String,
     path: PathBuf,
+    /// The profile as written in its own file, without anything it inherits.
+    raw: ModProfile,
+    /// The profile with all of the profiles it extends merged in.
     profile: ModProfile,
+    /// The merged profiles this profile extends, with absolute paths.
+    inherited: Option<ModProfile>,
+    /// Resolved paths of the profiles this profile directly extends.
+    extends: Vec<PathBuf>,
+    /// Profile files that each native was last defined in, keyed by native ID.
+    native_sources: HashMap<String, PathBuf>,
+    /// Profile files that each package or file mapping was last defined in, keyed by ID.
+    package_sources: HashMap<String, PathBuf>,
 }
 
 impl Profile {
@@ -40,7 +60,12 @@ impl Profile {
         Self {
             name: "transient-profile".to_string(),
             path: Default::default(),
+            raw: Default::default(),
             profile: Default::default(),
+            inherited: Default::default(),
+            extends: Default::default(),
+            native_sources: Default::default(),
+            package_sources: Default::default(),
         }
     }
 
@@ -54,6 +79,74 @@ impl Profile {
         self.path.parent()
     }
 
+    /// Get the path to this profile file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the profile format version this profile was written in.
+    pub fn version(&self) -> &'static str {
+        self.raw.version()
+    }
+
+    /// Get the path of the lockfile recording the files loaded by this profile.
+    pub fn lock_path(&self) -> PathBuf {
+        lock::lock_path(&self.path)
+    }
+
+    /// Get the resolved paths of the profiles this profile directly extends.
+    pub fn extends(&self) -> &[PathBuf] {
+        &self.extends
+    }
+
+    /// Get the profile that is inherited from the profiles this profile extends, if any.
+    pub fn inherited(&self) -> Option<&ModProfile> {
+        self.inherited.as_ref()
+    }
+
+    /// Get the path of the profile file that `native` was defined in.
+    pub fn native_source(&self, native: &Native) -> Option<&Path> {
+        self.native_sources.get(&native.id()).map(PathBuf::as_path)
+    }
+
+    /// Get the path of the profile file that `package` was defined in.
+    pub fn package_source(&self, package: &Package) -> Option<&Path> {
+        self.package_sources
+            .get(&package.id())
+            .map(PathBuf::as_path)
+    }
+
+    /// Get the path of the profile file that `file` was defined in.
+    pub fn file_source(&self, file: &FileMapping) -> Option<&Path> {
+        self.package_sources.get(&file.id()).map(PathBuf::as_path)
+    }
+
+    /// Apply the natives, packages and options that are specific to `game`.
+    pub fn for_game(self, game: Game) -> Self {
+        Self {
+            profile: self.profile.for_game(game),
+            ..self
+        }
+    }
+
+    /// Upgrade this profile to the latest profile format version, returning `true` if the
+    /// profile was changed.
+    ///
+    /// Only this profile's own file is upgraded, profiles it extends are left untouched.
+    pub fn upgrade(&mut self) -> bool {
+        let old_version = self.raw.version();
+        self.raw = std::mem::take(&mut self.raw).upgrade();
+        self.profile = std::mem::take(&mut self.profile).upgrade();
+
+        old_version != self.raw.version()
+    }
+
+    /// Write this profile back to the file it was loaded from.
+    pub fn save(&self) -> color_eyre::Result<()> {
+        self.raw.to_file(&self.path)?;
+        Ok(())
+    }
+
     /// Get the single game this profile supports, or None if it supports multiple games/omits
     /// support metadata.
     pub fn supported_game(&self) -> Option<Game> {
@@ -64,6 +157,29 @@ impl Profile {
         }
     }
 
+    /// Get the games this profile supports, including the games of the profiles it extends.
+    pub fn supports(&self) -> Vec<Supports> {
+        self.profile.supports()
+    }
+
+    /// The oldest version of `game` the profile supports, if it sets one.
+    pub fn required_version(&self, game: Game) -> color_eyre::Result<Option<Version>> {
+        let Some(since) = self
+            .supports()
+            .into_iter()
+            .find(|support| support.game == game)
+            .and_then(|support| support.since_version)
+        else {
+            return Ok(None);
+        };
+
+        let version = since.parse().wrap_err_with(|| {
+            format!("{} has an invalid `since` version for {game}", self.name())
+        })?;
+
+        Ok(Some(version))
+    }
+
     /// Get an unordered list of natives to be loaded by this profile.
     ///
     /// See [compile] to produce an ordered list.
@@ -78,6 +194,26 @@ impl Profile {
         self.profile.packages().into_iter()
     }
 
+    /// Get an unordered list of single file mappings loaded by this profile.
+    ///
+    /// See [compile] to produce an ordered list.
+    pub fn files(&self) -> impl Iterator<Item = FileMapping> {
+        self.profile.files().into_iter()
+    }
+
+    /// Get the metadata of a package, including the fields that are read from the
+    /// `me3-package.toml` in the package directory.
+    pub fn package_metadata(&self, package: &Package) -> color_eyre::Result<Metadata> {
+        let mut package = package.clone();
+        package
+            .source_mut()
+            .make_absolute(self.base_dir().unwrap_or(Path::new(".")));
+
+        load_manifest(&mut package)?;
+
+        Ok(package.metadata)
+    }
+
     /// Get the savefile name that may be overridden by this profile.
     pub fn savefile(&self) -> Option<String> {
         self.profile.savefile()
@@ -92,8 +228,41 @@ impl Profile {
         }
     }
 
+    /// Resolve the path of `native`, expanding it into a native for each DLL it matches if it is
+    /// a glob pattern or a directory.
+    pub fn expand_native(
+        &self,
+        native: Native,
+        variables: &PathVariables,
+    ) -> color_eyre::Result<Vec<Native>> {
+        self.expand_native_with(native, &self.variables(variables))
+    }
+
+    fn expand_native_with(
+        &self,
+        mut native: Native,
+        variables: &PathVariables,
+    ) -> color_eyre::Result<Vec<Native>> {
+        resolve_source(&mut native, self.base_dir(), variables)?;
+        glob::expand_native(native)
+    }
+
+    /// `variables` with `${PROFILE_DIR}` set to this profile's directory.
+    fn variables(&self, variables: &PathVariables) -> PathVariables {
+        let mut variables = variables.clone();
+        variables.set(PROFILE_DIR, self.base_dir().unwrap_or(Path::new(".")));
+        variables
+    }
+
     /// Compile this profile into a load order of native DLLs and packages to be loaded.
-    pub fn compile(&self) -> color_eyre::Result<(Vec<Native>, Vec<Package>)> {
+    ///
+    /// File mappings are compiled into packages that serve a single file, and natives with a glob
+    /// pattern or directory as their path into a native for each DLL they match. Variables in
+    /// paths are expanded using `variables`, with `${PROFILE_DIR}` set to this profile's directory.
+    pub fn compile(
+        &self,
+        variables: &PathVariables,
+    ) -> color_eyre::Result<(Vec<Native>, Vec<Package>)> {
         fn exists<S: WithPackageSource>(p: &S) -> bool {
             match p.source().try_exists() {
                 Ok(true) => true,
@@ -104,23 +273,29 @@ impl Profile {
             }
         }
 
-        fn canonicalize<S: WithPackageSource>(base_dir: &Path, sources: &mut Vec<S>) {
-            sources
-                .iter_mut()
-                .for_each(|pkg| pkg.source_mut().make_absolute(base_dir));
-            sources.retain(exists);
+        let mut packages = self.profile.packages();
+        packages.extend(self.profile.files().into_iter().map(Package::from));
+
+        let variables = self.variables(variables);
+
+        for package in &mut packages {
+            resolve_source(package, self.base_dir(), &variables)?;
         }
 
-        let mut packages = self.profile.packages();
-        let mut natives = self.profile.natives();
+        let mut natives = vec![];
+
+        for native in self.profile.natives() {
+            natives.extend(self.expand_native_with(native, &variables)?);
+        }
 
-        let base_dir = self.base_dir().unwrap_or(Path::new("."));
+        packages.retain(exists);
+        natives.retain(exists);
 
-        canonicalize(base_dir, &mut packages);
-        canonicalize(base_dir, &mut natives);
+        for package in &mut packages {
+            load_manifest(package)?;
+        }
 
-        let mut ordered_natives = sort_dependencies(natives)?;
-        let mut ordered_packages = sort_dependencies(packages)?;
+        let (mut ordered_natives, mut ordered_packages) = sort_load_order(natives, packages)?;
 
         ordered_natives.retain(|native| native.enabled);
         ordered_packages.retain(|package| package.enabled);
@@ -129,11 +304,36 @@ impl Profile {
     }
 }
 
+/// Make the path of `source` absolute and expand the variables in it.
+fn resolve_source<S: WithPackageSource>(
+    source: &mut S,
+    base_dir: Option<&Path>,
+    variables: &PathVariables,
+) -> color_eyre::Result<()> {
+    let source = source.source_mut();
+    source.make_absolute(base_dir.unwrap_or(Path::new(".")));
+    source.expand(variables)?;
+
+    Ok(())
+}
+
+fn load_manifest(package: &mut Package) -> color_eyre::Result<()> {
+    package.load_manifest().wrap_err_with(|| {
+        format!(
+            "failed to read the package manifest of {}",
+            package.source().display()
+        )
+    })
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ProfileDbError {
     #[error("no profile named {0} could be found")]
     MissingProfileFile(Box<Path>),
 
+    #[error("profile inheritance cycle detected: {0}")]
+    CyclicExtends(String),
+
     #[error("unexpected IO error reading {path}: {inner}")]
     Other {
         path: Box<Path>,
@@ -143,9 +343,17 @@ pub enum ProfileDbError {
 
 impl ProfileDb {
     pub fn load<P: AsRef<Path>>(&self, path: P) -> color_eyre::Result<Profile> {
-        let path = path.as_ref();
+        let path = self.find(path.as_ref())?;
+
+        self.load_layered(&path, &mut vec![])
+    }
+
+    /// Find the profile file referred to by `path`, which is either an absolute path to a file
+    /// or the name of a profile in one of the search paths.
+    pub fn find(&self, path: &Path) -> Result<Box<Path>, ProfileDbError> {
         let is_file_ref = path.is_absolute() && path.exists();
-        let canonical_path = is_file_ref
+
+        is_file_ref
             .then_some(Box::from(path))
             .or_else(|| {
                 self.search_paths
@@ -168,28 +376,110 @@ impl ProfileDb {
                     })
                     .next_back()
             })
-            .ok_or_else(|| ProfileDbError::MissingProfileFile(Box::from(path)))?;
+            .ok_or_else(|| ProfileDbError::MissingProfileFile(Box::from(path)))
+    }
 
-        let normalized_path = canonical_path
+    /// Find a profile referred to by an `extends` entry of the profile in `base_dir`, preferring
+    /// paths relative to the extending profile over names in the search paths.
+    fn find_extended(&self, base_dir: &Path, path: &Path) -> Result<Box<Path>, ProfileDbError> {
+        let relative = base_dir.join(path);
+
+        if relative.is_file() {
+            return Ok(relative.into_boxed_path());
+        }
+
+        self.find(path)
+    }
+
+    /// Load the profile at `path` and merge in all of the profiles it extends, using `stack` to
+    /// detect inheritance cycles.
+    fn load_layered(&self, path: &Path, stack: &mut Vec<PathBuf>) -> color_eyre::Result<Profile> {
+        let normalized_path = path
             .normalize()
             .map_err(|inner| ProfileDbError::Other {
                 path: Box::from(path),
                 inner,
             })
-            .wrap_err("failed while normalizing")?;
+            .wrap_err("failed while normalizing")?
+            .into_path_buf();
+
+        if let Some(start) = stack.iter().position(|p| *p == normalized_path) {
+            let cycle = stack[start..]
+                .iter()
+                .chain(Some(&normalized_path))
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            return Err(ProfileDbError::CyclicExtends(cycle).into());
+        }
+
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        let raw = ModProfile::from_file(path)?;
+        let base_dir = normalized_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
 
-        let name = canonical_path
-            .file_stem()
-            .unwrap()
-            .to_string_lossy()
-            .to_string();
+        stack.push(normalized_path.clone());
 
-        let profile = ModProfile::from_file(&canonical_path)?;
+        let mut inherited: Option<ModProfile> = None;
+        let mut extends = vec![];
+        let mut native_sources = HashMap::new();
+        let mut package_sources = HashMap::new();
+
+        for extended in raw.extends() {
+            let extended_path = self.find_extended(&base_dir, &extended)?;
+            let mut base = self.load_layered(&extended_path, stack).wrap_err_with(|| {
+                format!("failed to load extended profile {}", extended.display())
+            })?;
+
+            if let Some(base_dir) = base.path.parent() {
+                base.profile.make_absolute(base_dir);
+            }
+
+            native_sources.extend(base.native_sources);
+            package_sources.extend(base.package_sources);
+            extends.push(base.path);
+
+            inherited = Some(match inherited {
+                Some(inherited) => base.profile.inherit(inherited),
+                None => base.profile,
+            });
+        }
+
+        stack.pop();
+
+        native_sources.extend(
+            raw.natives()
+                .iter()
+                .map(|native| (native.id(), normalized_path.clone())),
+        );
+        package_sources.extend(
+            raw.packages()
+                .iter()
+                .map(|package| (package.id(), normalized_path.clone())),
+        );
+        package_sources.extend(
+            raw.files()
+                .iter()
+                .map(|file| (file.id(), normalized_path.clone())),
+        );
+
+        let profile = match &inherited {
+            Some(inherited) => raw.clone().inherit(inherited.clone()),
+            None => raw.clone(),
+        };
 
         Ok(Profile {
             name,
-            path: normalized_path.into_path_buf(),
+            path: normalized_path,
+            raw,
             profile,
+            inherited,
+            extends,
+            native_sources,
+            package_sources,
         })
     }
 
@@ -215,8 +505,13 @@ mod test {
     use std::error::Error;
 
     use assert_fs::prelude::{FileTouch, FileWriteStr, PathChild};
+    use me3_mod_protocol::{
+        dependency::Dependency,
+        package::WithPackageSource,
+        variables::{PathVariables, GAME_DIR},
+    };
 
-    use super::ProfileDb;
+    use super::{ProfileDb, ProfileDbError};
 
     #[test]
     fn lists_me3_files() -> Result<(), Box<dyn Error>> {
@@ -272,6 +567,33 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    pub fn upgrade_v1_me3_file_in_place() -> Result<(), Box<dyn Error>> {
+        let db = ProfileDb {
+            search_paths: vec![],
+        };
+        let temp_file = assert_fs::NamedTempFile::new("my-profile.me3")?;
+        temp_file.write_str(
+            r#"
+profileVersion = 'v1'
+
+[[packages]]
+id = 'my-mod'
+path = 'mod/'
+"#,
+        )?;
+
+        let mut profile = db.load(temp_file.path())?;
+        assert!(profile.upgrade());
+        profile.save()?;
+
+        let upgraded = db.load(temp_file.path())?;
+        assert_eq!("v2", upgraded.version());
+        assert_eq!(1, upgraded.packages().count());
+
+        Ok(())
+    }
+
     #[test]
     pub fn load_relative_me3_profile_name_from_search_path() -> Result<(), Box<dyn Error>> {
         let temp_dir = assert_fs::TempDir::new()?;
@@ -288,4 +610,129 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    pub fn load_profile_with_extends() -> Result<(), Box<dyn Error>> {
+        let temp_dir = assert_fs::TempDir::new()?;
+        temp_dir.child("base.me3").write_str(
+            r#"
+profileVersion = 'v1'
+savefile = 'base.sl2'
+start_online = false
+
+[[packages]]
+id = 'shared'
+path = 'base-shared/'
+
+[[packages]]
+id = 'base-only'
+path = 'base-only/'
+"#,
+        )?;
+        temp_dir.child("child/child.me3").write_str(
+            r#"
+profileVersion = 'v1'
+extends = ['../base.me3']
+savefile = 'child.sl2'
+
+[[packages]]
+id = 'shared'
+path = 'child-shared/'
+"#,
+        )?;
+
+        let db = ProfileDb {
+            search_paths: vec![],
+        };
+
+        let profile = db.load(temp_dir.child("child/child.me3").path())?;
+        let packages: Vec<_> = profile.packages().collect();
+
+        assert_eq!(1, profile.extends().len());
+        assert_eq!(2, packages.len());
+        assert_eq!("shared", packages[0].id());
+        assert!(packages[0].source().is_relative());
+        assert_eq!(Some(profile.path()), profile.package_source(&packages[0]));
+        assert_eq!("base-only", packages[1].id());
+        assert!(packages[1]
+            .source()
+            .starts_with(profile.extends()[0].parent().unwrap()));
+        assert_eq!(Some("child.sl2".to_string()), profile.savefile());
+        assert_eq!(Some(false), profile.options().start_online);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn compile_expands_path_variables() -> Result<(), Box<dyn Error>> {
+        let temp_dir = assert_fs::TempDir::new()?;
+        temp_dir.child("base/base-mod/regulation.bin").touch()?;
+        temp_dir.child("game/mods/fixes.dll").touch()?;
+        temp_dir.child("base/base.me3").write_str(
+            r#"
+profileVersion = 'v1'
+
+[[packages]]
+id = 'base-mod'
+path = '${PROFILE_DIR}/base-mod'
+"#,
+        )?;
+        temp_dir.child("child.me3").write_str(
+            r#"
+profileVersion = 'v1'
+extends = ['base/base.me3']
+
+[[natives]]
+path = '${GAME_DIR}/mods/fixes.dll'
+"#,
+        )?;
+
+        let db = ProfileDb {
+            search_paths: vec![],
+        };
+
+        let profile = db.load(temp_dir.child("child.me3").path())?;
+
+        let error = profile.compile(&PathVariables::new()).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("the game directory is not known"));
+
+        let mut variables = PathVariables::new();
+        variables.set(GAME_DIR, temp_dir.child("game").path());
+
+        let (natives, packages) = profile.compile(&variables)?;
+
+        assert_eq!(1, natives.len());
+        assert!(natives[0].source().ends_with("game/mods/fixes.dll"));
+        assert_eq!(1, packages.len());
+        assert!(packages[0].source().ends_with("base/base-mod"));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn load_profile_with_cyclic_extends() -> Result<(), Box<dyn Error>> {
+        let temp_dir = assert_fs::TempDir::new()?;
+        temp_dir
+            .child("a.me3")
+            .write_str("profileVersion = 'v1'\nextends = ['b']")?;
+        temp_dir
+            .child("b.me3")
+            .write_str("profileVersion = 'v1'\nextends = ['a']")?;
+
+        let db = ProfileDb {
+            search_paths: vec![Box::from(temp_dir.path())],
+        };
+
+        let error = db.load("a").err().expect("cycle was not detected");
+        let cycle = error
+            .chain()
+            .find_map(|e| e.downcast_ref::<ProfileDbError>())
+            .expect("expected a ProfileDbError");
+
+        assert!(matches!(cycle, ProfileDbError::CyclicExtends(_)));
+
+        Ok(())
+    }
 }
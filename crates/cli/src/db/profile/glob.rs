@@ -0,0 +1,201 @@
+//! Natives that point to a glob pattern or a directory, which load every DLL they match.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Context;
+use globset::{GlobBuilder, GlobMatcher};
+use me3_mod_protocol::{native::Native, package::WithPackageSource};
+use tracing::warn;
+
+/// Expands a native with a glob pattern or directory as its path into a native for each DLL it
+/// matches, ordered by path. Other natives are returned unchanged.
+pub(super) fn expand_native(native: Native) -> color_eyre::Result<Vec<Native>> {
+    let source = native.source();
+
+    let (base, paths) = if source.is_dir() {
+        (source.to_path_buf(), dlls_in(source))
+    } else if source.is_pattern() {
+        let (base, pattern) = split_pattern(source);
+        let paths = glob(&base, &pattern);
+        (base, paths)
+    } else {
+        return Ok(vec![native]);
+    };
+
+    let paths = paths
+        .wrap_err_with(|| format!("failed to find the natives matching {}", source.display()))?;
+
+    if paths.is_empty() {
+        warn!(path = %source.display(), "native path does not match any DLLs");
+    }
+
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let relative = relative_path(&base, &path);
+            native.for_match(path, &relative)
+        })
+        .collect())
+}
+
+/// Splits a glob pattern into the directory it is matched in and the components below it, up to
+/// the first component that contains a wildcard.
+fn split_pattern(pattern: &Path) -> (PathBuf, Vec<String>) {
+    let components = pattern
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+
+    let split = components
+        .iter()
+        .position(|component| component.contains(['*', '?', '[', '{']))
+        .unwrap_or(components.len());
+
+    let base = pattern.components().take(split).collect::<PathBuf>();
+
+    (base, components[split..].to_vec())
+}
+
+/// `path` relative to `base`, with `/` as the separator.
+fn relative_path(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The DLLs directly inside `dir`.
+fn dlls_in(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut dlls = vec![];
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        let is_dll = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("dll"));
+
+        if is_dll && path.is_file() {
+            dlls.push(path);
+        }
+    }
+
+    dlls.sort();
+    Ok(dlls)
+}
+
+/// The files below `base` matching the components of `relative`, which are matched case
+/// insensitively. `*` does not match across directories, so only the directories `**` can match
+/// are searched recursively.
+fn glob(base: &Path, relative: &[String]) -> io::Result<Vec<PathBuf>> {
+    let matcher = GlobBuilder::new(&relative.join("/"))
+        .case_insensitive(true)
+        .literal_separator(true)
+        .backslash_escape(false)
+        .build()
+        .map_err(io::Error::other)?
+        .compile_matcher();
+
+    let walk = Walk {
+        base,
+        matcher,
+        max_depth: (!relative.iter().any(|component| component.contains("**")))
+            .then_some(relative.len()),
+    };
+
+    let mut matches = vec![];
+    walk.walk(base, 1, &mut matches)?;
+
+    matches.sort();
+    Ok(matches)
+}
+
+struct Walk<'a> {
+    base: &'a Path,
+    matcher: GlobMatcher,
+    /// The number of directories below `base` that can contain matches, if it is limited.
+    max_depth: Option<usize>,
+}
+
+impl Walk<'_> {
+    fn walk(&self, dir: &Path, depth: usize, matches: &mut Vec<PathBuf>) -> io::Result<()> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                if self.max_depth.is_none_or(|max_depth| depth < max_depth) {
+                    self.walk(&path, depth + 1, matches)?;
+                }
+
+                continue;
+            }
+
+            if self.matcher.is_match(relative_path(self.base, &path)) {
+                matches.push(path);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+
+    use assert_fs::prelude::{FileTouch, PathChild};
+    use me3_mod_protocol::{dependency::Dependency, native::Native};
+
+    use super::expand_native;
+
+    #[test]
+    fn expands_patterns_and_directories() -> Result<(), Box<dyn Error>> {
+        let temp_dir = assert_fs::TempDir::new()?;
+
+        for file in [
+            "plugins/b.dll",
+            "plugins/A.DLL",
+            "plugins/readme.txt",
+            "plugins/nested/c.dll",
+        ] {
+            temp_dir.child(file).touch()?;
+        }
+
+        let ids = |native: Native| -> Result<Vec<String>, Box<dyn Error>> {
+            Ok(expand_native(native)?.iter().map(Dependency::id).collect())
+        };
+
+        let mut native = Native::new(temp_dir.child("plugins/*.dll").path());
+        native.optional = true;
+
+        let expanded = expand_native(native.clone())?;
+        assert_eq!(["*.dll/A.DLL", "*.dll/b.dll"], ids(native)?.as_slice());
+        assert!(expanded.iter().all(|native| native.optional));
+        assert!(expanded
+            .iter()
+            .all(|native| native.group() == Some("*.dll")));
+
+        assert_eq!(
+            ["*.dll/A.DLL", "*.dll/b.dll", "*.dll/nested/c.dll"],
+            ids(Native::new(temp_dir.child("plugins/**/*.dll").path()))?.as_slice()
+        );
+        assert_eq!(
+            ["plugins/A.DLL", "plugins/b.dll"],
+            ids(Native::new(temp_dir.child("plugins").path()))?.as_slice()
+        );
+        assert!(ids(Native::new(temp_dir.child("missing/*.dll").path()))?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+//! Lockfiles recording the contents of every native and package file a profile loads, so that
+//! players can check they are running byte-identical mods.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Context};
+use me3_mod_protocol::{
+    dependency::Dependency,
+    native::Native,
+    package::{Package, WithPackageSource},
+    Game,
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Incremented whenever the layout of [`ProfileLock`] changes.
+const LOCK_VERSION: u32 = 1;
+
+/// Content hashes of the natives and package files loaded by a profile, keyed by the ID of the
+/// native or package so that the lockfile can be verified on machines with different paths.
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct ProfileLock {
+    version: u32,
+
+    /// The game the profile was compiled for.
+    #[serde(default)]
+    game: Option<Game>,
+
+    /// Hashes of native DLLs, keyed by native ID.
+    #[serde(default)]
+    natives: BTreeMap<String, String>,
+
+    /// Hashes of package files, keyed by package ID and the path of the file in the package.
+    #[serde(default)]
+    packages: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Differences between a lockfile and the files that are currently loaded.
+#[derive(Debug, Default)]
+pub struct LockDiff {
+    /// The game the lockfile was created for, if it isn't the game that is launched. Lockfiles
+    /// created without a game apply to every game.
+    pub game: Option<Game>,
+    pub changed: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl LockDiff {
+    pub fn is_empty(&self) -> bool {
+        self.game.is_none()
+            && self.changed.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+    }
+}
+
+impl ProfileLock {
+    /// Hashes the given natives and every file of the given packages.
+    pub fn create(
+        game: Option<Game>,
+        natives: &[Native],
+        packages: &[Package],
+    ) -> color_eyre::Result<Self> {
+        let natives = natives
+            .iter()
+            .map(|native| Ok((native.id(), hash_file(native.source())?)))
+            .collect::<color_eyre::Result<_>>()?;
+
+        let packages = packages
+            .iter()
+            .map(|package| Ok((package.id(), hash_package(package.source())?)))
+            .collect::<color_eyre::Result<_>>()?;
+
+        Ok(Self {
+            version: LOCK_VERSION,
+            game,
+            natives,
+            packages,
+        })
+    }
+
+    /// Loads the lockfile at `path`, if there is one.
+    pub fn load(path: &Path) -> color_eyre::Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).wrap_err_with(|| format!("failed to read {}", path.display())),
+        };
+
+        let lock: Self = toml::from_str(&contents)
+            .wrap_err_with(|| format!("failed to parse {}", path.display()))?;
+
+        if lock.version != LOCK_VERSION {
+            return Err(eyre!(
+                "{} was created by a different version of me3, recreate it with `me3 profile lock`",
+                path.display()
+            ));
+        }
+
+        Ok(Some(lock))
+    }
+
+    pub fn save(&self, path: &Path) -> color_eyre::Result<()> {
+        fs::write(path, toml::to_string_pretty(self)?)
+            .wrap_err_with(|| format!("failed to write {}", path.display()))
+    }
+
+    /// The number of files recorded in the lockfile.
+    pub fn file_count(&self) -> usize {
+        self.natives.len() + self.packages.values().map(BTreeMap::len).sum::<usize>()
+    }
+
+    /// Compares the lockfile to the files that are `current`ly loaded.
+    pub fn diff(&self, current: &Self) -> LockDiff {
+        let locked = self.files();
+        let current_files = current.files();
+
+        let mut diff = LockDiff {
+            game: self.game.filter(|&game| current.game != Some(game)),
+            ..Default::default()
+        };
+
+        for (name, hash) in &current_files {
+            match locked.get(name) {
+                Some(locked_hash) if locked_hash == hash => {}
+                Some(_) => diff.changed.push(name.clone()),
+                None => diff.added.push(name.clone()),
+            }
+        }
+
+        diff.removed = locked
+            .into_keys()
+            .filter(|name| !current_files.contains_key(name))
+            .collect();
+
+        diff
+    }
+
+    /// Every recorded file, named after the native or package it belongs to.
+    fn files(&self) -> BTreeMap<String, &str> {
+        let natives = self
+            .natives
+            .iter()
+            .map(|(id, hash)| (format!("native {id}"), hash.as_str()));
+
+        let packages = self.packages.iter().flat_map(|(id, files)| {
+            files
+                .iter()
+                .map(move |(path, hash)| (format!("package {id}: {path}"), hash.as_str()))
+        });
+
+        natives.chain(packages).collect()
+    }
+}
+
+/// The path of the lockfile of the profile at `profile_path`, e.g. `name.me3.lock`.
+pub fn lock_path(profile_path: &Path) -> PathBuf {
+    let mut path = profile_path.as_os_str().to_owned();
+    path.push(".lock");
+    path.into()
+}
+
+/// Hashes every file of a package directory, or the package file itself.
+fn hash_package(source: &Path) -> color_eyre::Result<BTreeMap<String, String>> {
+    if !source.is_dir() {
+        let name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        return Ok(BTreeMap::from([(name, hash_file(source)?)]));
+    }
+
+    let mut files = vec![];
+    collect_files(source, &mut files)
+        .wrap_err_with(|| format!("failed to list the files in {}", source.display()))?;
+
+    files
+        .into_par_iter()
+        .map(|path| {
+            let name = path
+                .strip_prefix(source)
+                .unwrap_or(&path)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            Ok((name, hash_file(&path)?))
+        })
+        .collect()
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn hash_file(path: &Path) -> color_eyre::Result<String> {
+    let hash = (|| {
+        let mut file = File::open(path)?;
+        let mut hasher = Xxh3::new();
+        let mut buf = vec![0; 64 * 1024];
+
+        loop {
+            match file.read(&mut buf)? {
+                0 => break,
+                len => hasher.update(&buf[..len]),
+            }
+        }
+
+        io::Result::Ok(hasher.digest128())
+    })()
+    .wrap_err_with(|| format!("failed to hash {}", path.display()))?;
+
+    Ok(format!("xxh3:{hash:032x}"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+
+    use assert_fs::prelude::{FileWriteStr, PathChild};
+    use me3_mod_protocol::{dependency::Dependency, native::Native, package::Package, Game};
+
+    use super::ProfileLock;
+
+    #[test]
+    fn diff_reports_changed_added_and_removed_files() -> Result<(), Box<dyn Error>> {
+        let temp_dir = assert_fs::TempDir::new()?;
+        temp_dir.child("mod.dll").write_str("native")?;
+        temp_dir.child("package/regulation.bin").write_str("v1")?;
+        temp_dir.child("package/parts/a.dcx").write_str("a")?;
+
+        let natives = [Native::new(temp_dir.child("mod.dll").path())];
+        let packages = [Package::new(temp_dir.child("package").to_path_buf())];
+
+        let lock = ProfileLock::create(None, &natives, &packages)?;
+        assert_eq!(3, lock.file_count());
+
+        let lock_path = temp_dir.child("profile.me3.lock");
+        lock.save(&lock_path)?;
+        let lock = ProfileLock::load(&lock_path)?.unwrap();
+
+        assert!(lock
+            .diff(&ProfileLock::create(None, &natives, &packages)?)
+            .is_empty());
+
+        temp_dir.child("package/regulation.bin").write_str("v2")?;
+        temp_dir.child("package/parts/b.dcx").write_str("b")?;
+        std::fs::remove_file(temp_dir.child("package/parts/a.dcx"))?;
+
+        let package_id = packages[0].id();
+        let diff = lock.diff(&ProfileLock::create(None, &natives, &packages)?);
+
+        assert_eq!(
+            vec![format!("package {package_id}: regulation.bin")],
+            diff.changed
+        );
+        assert_eq!(
+            vec![format!("package {package_id}: parts/b.dcx")],
+            diff.added
+        );
+        assert_eq!(
+            vec![format!("package {package_id}: parts/a.dcx")],
+            diff.removed
+        );
+        assert!(diff.game.is_none());
+
+        let current = ProfileLock::create(Some(Game::EldenRing), &natives, &packages)?;
+        assert!(lock.diff(&current).game.is_none());
+
+        let lock = ProfileLock::create(Some(Game::DarkSouls3), &natives, &packages)?;
+        assert_eq!(lock.diff(&current).game, Some(Game::DarkSouls3));
+
+        Ok(())
+    }
+}
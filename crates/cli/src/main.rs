@@ -1,9 +1,4 @@
-use std::{
-    io::stderr,
-    iter,
-    path::{Path, PathBuf},
-    slice,
-};
+use std::{io::stderr, iter, path::PathBuf, slice};
 
 use clap::{builder::PossibleValue, ArgAction, Parser, ValueEnum};
 use commands::{profile::ProfileCommands, Commands};
@@ -71,24 +66,6 @@ impl ValueEnum for Game {
     }
 }
 
-impl Game {
-    fn app_id(self) -> u32 {
-        self.0.app_id()
-    }
-
-    fn from_app_id(id: u32) -> Option<Self> {
-        me3_mod_protocol::Game::from_app_id(id).map(Self)
-    }
-
-    fn launcher(self) -> &'static Path {
-        self.0.executable()
-    }
-
-    fn into_vars(self) -> me3_env::GameVars {
-        me3_env::GameVars { launched: self.0 }
-    }
-}
-
 impl From<Game> for me3_mod_protocol::Game {
     fn from(val: Game) -> Self {
         val.0
@@ -132,14 +109,36 @@ fn main() {
         commit_id = option_env!("BUILD_COMMIT_ID").unwrap_or("unknown")
     );
 
+    // Invalid game definitions are reported by the commands that use them.
+    if let Ok(games) = config.games() {
+        games.configure();
+    }
+
     let db = DbContext::new(&config);
 
     let result = me3_telemetry::with_root_span("me3", "run command", || match cli.command {
         Commands::Info => commands::info::info(config),
+        Commands::Analyze(args) => commands::analyze::analyze(config, args),
         Commands::Launch(args) => commands::launch::launch(db, config, args),
         Commands::Profile(ProfileCommands::Create(args)) => commands::profile::create(config, args),
         Commands::Profile(ProfileCommands::List) => commands::profile::list(db),
         Commands::Profile(ProfileCommands::Show(name)) => commands::profile::show(db, config, name),
+        Commands::Profile(ProfileCommands::Upgrade(name)) => {
+            commands::profile::upgrade(db, config, name)
+        }
+        Commands::Profile(ProfileCommands::Validate(name)) => {
+            commands::profile::validate(db, config, name)
+        }
+        Commands::Profile(ProfileCommands::Conflicts(args)) => {
+            commands::profile::conflicts(db, config, args)
+        }
+        Commands::Profile(ProfileCommands::Graph(args)) => {
+            commands::profile::graph(db, config, args)
+        }
+        Commands::Profile(ProfileCommands::Lock(args)) => commands::profile::lock(db, config, args),
+        Commands::Profile(ProfileCommands::Verify(args)) => {
+            commands::profile::verify(db, config, args)
+        }
         #[cfg(target_os = "windows")]
         Commands::AddToPath => commands::windows::add_to_path(),
         #[cfg(target_os = "windows")]
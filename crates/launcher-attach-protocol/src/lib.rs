@@ -18,18 +18,34 @@ pub struct AttachConfig {
     /// The attached to game.
     pub game: Game,
 
+    /// Fingerprint of the game executable, as produced by
+    /// `me3_binary_analysis::fingerprint::Fingerprint`.
+    pub game_fingerprint: Option<String>,
+
+    /// The known build of the game matching [`AttachConfig::game_fingerprint`].
+    pub game_build: Option<String>,
+
     /// An ordered list of natives to be loaded on attach.
+    #[serde(with = "as_json")]
     pub natives: Vec<Native>,
 
     /// An ordered list of packages to be loaded on attach.
+    #[serde(with = "as_json")]
     pub packages: Vec<Package>,
 
     /// Name of an alternative savefile to use (in the default savefile directory).
     pub savefile: Option<String>,
 
+    /// The default savefile directory of the game, which may refer to environment variables of the
+    /// game process as `${NAME}`.
+    pub savefile_dir: Option<PathBuf>,
+
     /// Path to the cache directory.
     pub cache_path: Option<PathBuf>,
 
+    /// Scan every package instead of reusing package indexes in the cache directory?
+    pub rescan_packages: bool,
+
     /// Suspend the game until a debugger is attached?
     pub suspend: bool,
 
@@ -52,6 +68,35 @@ pub struct AttachConfig {
     pub skip_steam_init: bool,
 }
 
+/// Natives and packages leave out empty fields to keep profiles tidy, which the non
+/// self-describing format the request is passed to the host in can't represent. They are embedded
+/// as JSON instead.
+mod as_json {
+    use serde::{
+        de::{DeserializeOwned, Error as _},
+        ser::Error as _,
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        let json = serde_json::to_string(value).map_err(S::Error::custom)?;
+        serializer.serialize_str(&json)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: DeserializeOwned,
+        D: Deserializer<'de>,
+    {
+        let json = String::deserialize(deserializer)?;
+        serde_json::from_str(&json).map_err(D::Error::custom)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Attachment;
 
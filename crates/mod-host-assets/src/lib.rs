@@ -1,5 +1,9 @@
+#[cfg(all(windows, feature = "hooks"))]
 pub mod bhd5;
+#[cfg(all(windows, feature = "hooks"))]
 pub mod dl_device;
+#[cfg(all(windows, feature = "hooks"))]
 pub mod ebl;
 pub mod mapping;
+#[cfg(all(windows, feature = "hooks"))]
 pub mod wwise;
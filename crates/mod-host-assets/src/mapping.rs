@@ -1,46 +1,94 @@
 use std::{
-    borrow::Borrow,
     collections::HashMap,
     env,
     ffi::OsStr,
-    fmt,
-    fs::read_dir,
-    io, iter,
-    os::windows::{ffi::OsStrExt as WinOsStrExt, fs::FileTypeExt},
+    fmt, io, iter,
     path::{Path, PathBuf, StripPrefixError},
+    sync::OnceLock,
 };
 
-use me3_mod_protocol::package::{AssetOverrideSource, Package};
-use normpath::PathExt;
-use rayon::iter::{ParallelBridge, ParallelIterator};
-use smallvec::{smallvec_inline, SmallVec};
+use me3_mod_protocol::{
+    conflicts::{AssetConflict, PackageOrdering},
+    package::{AssetOverrideSource, Package},
+};
+use smallvec::SmallVec;
 use thiserror::Error;
-use windows::core::{PCSTR, PCWSTR};
+use tracing::{debug, error, info, warn};
+
+use self::{
+    archive::{Archive, ArchiveEntry},
+    filter::AssetFilter,
+    index::{ScanIndex, StaleIndex},
+    key::VfsKey,
+};
 
+mod archive;
+mod filter;
+mod index;
+mod key;
 mod savefile;
+#[cfg(windows)]
+mod windows;
+
+/// Suffix of the marker files that hide the asset they are named after.
+const WHITEOUT_SUFFIX: &str = ".me3-hide";
+
+/// Assets discovered in a package, keyed relative to the package source.
+type ScannedAssets = Vec<(VfsKey, VfsOverride)>;
 
 pub struct VfsOverrideMapping {
-    map: HashMap<VfsKey, VfsOverride>,
+    /// Candidates for each asset in package load order, the last candidate takes precedence.
+    map: HashMap<VfsKey, SmallVec<[VfsOverride; 1]>>,
     current_dir: VfsKey,
+    /// Directory that files from archive packages are extracted to.
+    cache_dir: PathBuf,
+    /// Directory that package scan indexes are persisted in, if any.
+    index_dir: Option<PathBuf>,
+    force_rescan: bool,
     savefile_override: Option<savefile::SavefileOverrideMapping>,
 }
 
 pub struct VfsOverride {
     display: Box<str>,
-    path_c_str: Box<Path>,
+    path: OverridePath,
+    package: Option<Box<str>>,
+    roots: Box<[Box<str>]>,
+    hidden: bool,
+    archive: Option<Box<ArchiveOverride>>,
+}
+
+/// A nul-terminated path in the encodings that are passed to the game.
+struct OverridePath {
+    c_str: Box<Path>,
+    #[cfg(windows)]
     wide_c_str: Box<[u16]>,
 }
 
+/// An archive entry that an override serves, along with its extracted copy once it was opened.
+struct ArchiveOverride {
+    entry: ArchiveEntry,
+    extracted: OnceLock<Option<OverridePath>>,
+}
+
 #[derive(Debug, Error)]
 pub enum VfsOverrideMappingError {
     #[error("Package source specified is not a directory {0}.")]
     InvalidDirectory(PathBuf),
 
+    #[error("File mapping source specified is not a file {0}.")]
+    InvalidFile(PathBuf),
+
     #[error("Could not read directory while discovering override assets {0}")]
     ReadDir(io::Error),
 
     #[error("Could not acquire directory entry")]
     StripPrefix(#[from] StripPrefixError),
+
+    #[error("Invalid include or exclude pattern {0}")]
+    InvalidPattern(#[from] globset::Error),
+
+    #[error("Could not read package archive {0}: {1}")]
+    ReadArchive(PathBuf, zip::result::ZipError),
 }
 
 impl VfsOverrideMapping {
@@ -52,63 +100,209 @@ impl VfsOverrideMapping {
         Ok(Self {
             map: HashMap::new(),
             current_dir,
+            cache_dir: env::temp_dir().join("me3").join("packages"),
+            index_dir: None,
+            force_rescan: false,
             savefile_override: None,
         })
     }
 
+    /// Use `cache_dir` for files extracted from archive packages, instead of a temporary
+    /// directory, and to persist the contents of package directories so unchanged packages don't
+    /// need to be scanned again. Only applies to packages that are scanned afterwards.
+    pub fn set_cache_dir<P: AsRef<Path>>(&mut self, cache_dir: P) {
+        self.cache_dir = cache_dir.as_ref().join("packages");
+        self.index_dir = Some(cache_dir.as_ref().join("index"));
+    }
+
+    /// Scan package directories even if a persisted index for them is up to date.
+    pub fn set_force_rescan(&mut self, force_rescan: bool) {
+        self.force_rescan = force_rescan;
+    }
+
     /// Scans a set of directories, mapping discovered assets into itself.
     pub fn scan_directories<I>(&mut self, sources: I) -> Result<(), VfsOverrideMappingError>
     where
         I: Iterator<Item: AssetOverrideSource>,
     {
-        fn scan_directories_inner(
-            base_dir: &Path,
-            root_key: &VfsKey,
-        ) -> SmallVec<[Result<(VfsKey, VfsOverride), io::Error>; 1]> {
-            let entries = match read_dir(base_dir) {
-                Ok(entries) => entries,
-                Err(e) => return smallvec_inline![Err(e)],
+        for source in sources {
+            let source_path = source.asset_path();
+            let package = Box::<str>::from(source.source_id());
+            let package_roots = source
+                .asset_roots()
+                .iter()
+                .map(|root| key::normalize_root(root))
+                .collect::<Box<[_]>>();
+
+            if let Some(target) = source.asset_target() {
+                if !source_path.is_file() {
+                    return Err(VfsOverrideMappingError::InvalidFile(source_path.to_owned()));
+                }
+
+                let roots = match VfsKey::vfs_root(target) {
+                    Some(root) => Box::from([root]),
+                    None => package_roots,
+                };
+
+                let vfs_override = VfsOverride::new(source_path).with_package(package, roots);
+                self.insert(VfsKey::for_vfs_path(target), vfs_override);
+
+                continue;
+            }
+
+            for hidden in source.asset_hide() {
+                let roots = match VfsKey::vfs_root(hidden) {
+                    Some(root) => Box::from([root]),
+                    None => package_roots.clone(),
+                };
+
+                let vfs_override = VfsOverride::new(source_path)
+                    .with_package(package.clone(), roots)
+                    .hiding();
+
+                self.insert(VfsKey::for_vfs_path(hidden), vfs_override);
+            }
+
+            let filter = AssetFilter::new(source.asset_include(), source.asset_exclude())?;
+
+            let (mount_key, package_roots) = match source.asset_mount_point() {
+                Some(mount_point) => match VfsKey::vfs_root(mount_point) {
+                    Some(root) => (Some(VfsKey::for_vfs_path(mount_point)), Box::from([root])),
+                    None => (Some(VfsKey::for_vfs_path(mount_point)), package_roots),
+                },
+                None => (None, package_roots),
             };
 
-            let result = entries
-                .flatten()
-                .par_bridge()
-                .flat_map_iter(|dir_entry| match dir_entry.file_type() {
-                    Ok(file_type) if file_type.is_dir() || file_type.is_symlink_dir() => {
-                        scan_directories_inner(&dir_entry.path(), root_key)
-                    }
-                    Ok(_) => {
-                        let path = dir_entry.path();
+            let scanned_assets = if archive::is_archive(source_path) {
+                self.scan_archive(source_path)?
+            } else {
+                self.scan_package_directory(source_path, &package)?
+            };
 
-                        let result = VfsKey::for_asset_path(&path, root_key)
-                            .map(|vfs_key| (vfs_key, VfsOverride::new(&path)));
+            self.map.reserve(scanned_assets.len());
 
-                        smallvec_inline![result]
-                    }
-                    Err(e) => smallvec_inline![Err(e)],
-                })
-                .collect();
+            for (vfs_key, vfs_override) in scanned_assets {
+                if !filter.is_match(vfs_key.as_ref()) {
+                    continue;
+                }
 
-            SmallVec::from_vec(result)
-        }
+                let (vfs_key, roots) = match vfs_key.split_root() {
+                    (Some(root), vfs_key) => (vfs_key, Box::from([root])),
+                    (None, vfs_key) => (vfs_key, package_roots.clone()),
+                };
 
-        for source in sources {
-            let source_path = source.asset_path();
-            let root_key =
-                VfsKey::for_disk_path(source_path).map_err(VfsOverrideMappingError::ReadDir)?;
+                let vfs_key = match &mount_key {
+                    Some(mount_key) => mount_key.join(&vfs_key),
+                    None => vfs_key,
+                };
 
-            let scanned_directories = scan_directories_inner(source_path, &root_key);
-            self.map.reserve(scanned_directories.len());
+                let vfs_override = vfs_override.with_package(package.clone(), roots);
 
-            for result in scanned_directories {
-                let (vfs_key, vfs_override) = result.map_err(VfsOverrideMappingError::ReadDir)?;
-                self.map.insert(vfs_key, vfs_override);
+                match vfs_key.strip_suffix(WHITEOUT_SUFFIX) {
+                    Some(vfs_key) => self.insert(vfs_key, vfs_override.hiding()),
+                    None => self.insert(vfs_key, vfs_override),
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Lists the files of a package directory, reusing its persisted index if none of its
+    /// directories were modified since it was created.
+    fn scan_package_directory(
+        &self,
+        path: &Path,
+        package: &str,
+    ) -> Result<ScannedAssets, VfsOverrideMappingError> {
+        let index = match &self.index_dir {
+            Some(index_dir) => {
+                let index_path = index_dir
+                    .join(ScanIndex::file_name(path).map_err(VfsOverrideMappingError::ReadDir)?);
+
+                let persisted = match self.force_rescan {
+                    true => Err(StaleIndex::Rescan),
+                    false => ScanIndex::load(&index_path, path),
+                };
+
+                match persisted {
+                    Ok(index) => {
+                        info!(
+                            package,
+                            files = index.files().len(),
+                            "package index is up to date"
+                        );
+                        index
+                    }
+                    Err(reason) => {
+                        info!(package, %reason, "scanning package");
+
+                        let index =
+                            ScanIndex::scan(path).map_err(VfsOverrideMappingError::ReadDir)?;
+
+                        match index.save(&index_path) {
+                            Ok(true) => {}
+                            Ok(false) => {
+                                debug!(package, "package was modified too recently to be indexed")
+                            }
+                            Err(e) => warn!(package, error = %e, "failed to persist package index"),
+                        }
+
+                        index
+                    }
+                }
+            }
+            None => ScanIndex::scan(path).map_err(VfsOverrideMappingError::ReadDir)?,
+        };
+
+        let result = index
+            .files()
+            .iter()
+            .map(|file| (file.key(), VfsOverride::new(path.join(file.path()))))
+            .collect();
+
+        Ok(result)
+    }
+
+    /// Indexes the files of an archive package, which are only extracted once they are opened.
+    fn scan_archive(&self, path: &Path) -> Result<ScannedAssets, VfsOverrideMappingError> {
+        let read_error =
+            |e: zip::result::ZipError| VfsOverrideMappingError::ReadArchive(path.to_owned(), e);
+
+        let archive = Archive::open(path, &self.cache_dir).map_err(read_error)?;
+        let files = archive.files().map_err(read_error)?;
+
+        let result = files
+            .iter()
+            .map(|file| {
+                let entry = ArchiveEntry::new(archive.clone(), file);
+                let vfs_override = VfsOverride::new(entry.display_path()).in_archive(entry);
+
+                (VfsKey::for_vfs_path(&file.path), vfs_override)
+            })
+            .collect();
+
+        Ok(result)
+    }
+
+    /// Adds a candidate for an asset that takes precedence over the ones added before it.
+    fn insert(&mut self, vfs_key: VfsKey, vfs_override: VfsOverride) {
+        let candidates = self.map.entry(vfs_key).or_default();
+
+        if let Some(shadowed) = candidates
+            .iter()
+            .rfind(|candidate| candidate.overlaps(&vfs_override))
+        {
+            debug!(
+                %shadowed,
+                package = vfs_override.package(),
+                "asset is overridden by a later package"
+            );
+        }
+
+        candidates.push(vfs_override);
+    }
+
     pub fn scan_directory<P: AsRef<Path>>(
         &mut self,
         path: P,
@@ -127,6 +321,8 @@ impl VfsOverrideMapping {
         Ok(())
     }
 
+    /// The override for a vfs path with the highest precedence, which may be
+    /// [hidden](VfsOverride::is_hidden) to report the asset as absent.
     pub fn vfs_override<S: AsRef<OsStr>>(&self, path_str: S) -> Option<&VfsOverride> {
         let path = Path::new(&path_str);
 
@@ -137,68 +333,246 @@ impl VfsOverrideMapping {
             return Some(savefile_override_path);
         }
 
+        let root = VfsKey::vfs_root(path);
         let key = VfsKey::for_vfs_path(path);
-        self.map.get(&key)
+        self.map
+            .get(&key)
+            .and_then(|candidates| Self::served(candidates, root.as_deref()))
     }
 
+    /// The override for a disk path relative to the game directory. Hiding only applies to vfs
+    /// paths, disk paths are served unchanged if the asset is hidden.
     pub fn disk_override<S: AsRef<OsStr>>(&self, path_str: S) -> Option<&VfsOverride> {
         let key = VfsKey::for_asset_path(Path::new(&path_str), &self.current_dir).ok()?;
-        self.map.get(&key)
+        self.map
+            .get(&key)
+            .and_then(|candidates| Self::served(candidates, None))
+            .filter(|candidate| !candidate.is_hidden())
+    }
+
+    /// All candidates for a vfs path in package load order, including the ones shadowed by the
+    /// candidate that is served. Candidates limited to other mount roots are skipped.
+    pub fn vfs_candidates<S: AsRef<OsStr>>(
+        &self,
+        path_str: S,
+    ) -> impl Iterator<Item = &VfsOverride> {
+        let path = Path::new(&path_str);
+        let root = VfsKey::vfs_root(path);
+        let key = VfsKey::for_vfs_path(path);
+
+        self.map
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .filter(move |candidate| candidate.applies_to(root.as_deref()))
+    }
+
+    /// Asset paths that are provided by more than one package, with their candidates in package
+    /// load order. Candidates may be limited to different mount roots.
+    pub fn conflicts(&self) -> impl Iterator<Item = (&Path, &[VfsOverride])> {
+        self.map
+            .iter()
+            .filter(|(_, candidates)| candidates.len() > 1)
+            .map(|(key, candidates)| (key.as_ref(), &candidates[..]))
+    }
+
+    /// Describe every asset that is provided by more than one of `packages`, sorted by path.
+    ///
+    /// `packages` must be the packages this mapping was scanned from, in load order.
+    pub fn conflict_report(&self, packages: &[Package]) -> Vec<AssetConflict> {
+        let ordering = &PackageOrdering::new(packages);
+
+        let mut conflicts = self
+            .conflicts()
+            .flat_map(|(path, candidates)| {
+                // Candidates compete separately for every root that one of them is limited to,
+                // and once more for every other root.
+                let mut roots = vec![None];
+
+                for root in candidates.iter().flat_map(VfsOverride::roots) {
+                    if !roots.contains(&Some(root)) {
+                        roots.push(Some(root));
+                    }
+                }
+
+                roots.into_iter().filter_map(move |root| {
+                    let mut providers = Vec::<String>::with_capacity(candidates.len());
+
+                    let packages = candidates
+                        .iter()
+                        .filter(|candidate| candidate.applies_to(root))
+                        .filter_map(VfsOverride::package);
+
+                    for package in packages {
+                        if !providers.iter().any(|provider| provider == package) {
+                            providers.push(package.to_owned());
+                        }
+                    }
+
+                    let path = match root {
+                        Some(root) => Path::new(&format!("{root}:")).join(path),
+                        None => path.to_owned(),
+                    };
+
+                    (providers.len() > 1).then(|| ordering.conflict(path, providers))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+        conflicts
+    }
+
+    /// The candidate with the highest precedence that applies to the mount `root` of the looked up
+    /// path and can be served. Usually only that candidate is checked, shadowed candidates are
+    /// only served if its file was removed after scanning or it fails to extract.
+    /// Candidates from archive packages are extracted when they are first looked up.
+    fn served<'a>(candidates: &'a [VfsOverride], root: Option<&str>) -> Option<&'a VfsOverride> {
+        candidates
+            .iter()
+            .rev()
+            .filter(|candidate| candidate.applies_to(root))
+            .find(|candidate| candidate.is_available())
     }
 }
 
 impl VfsOverride {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
-        let display = path.as_ref().display().to_string().into_boxed_str();
+        Self {
+            display: path.as_ref().display().to_string().into_boxed_str(),
+            path: OverridePath::new(path.as_ref()),
+            package: None,
+            roots: Box::default(),
+            hidden: false,
+            archive: None,
+        }
+    }
 
-        let (wide_c_str, path_c_str) = {
-            let mut os_str = path.as_ref().as_os_str().to_os_string();
-            os_str.push("\0");
+    /// Makes this override serve an archive entry, which is extracted on demand. The override's
+    /// path is the path of the extracted copy once it was served.
+    fn in_archive(self, entry: ArchiveEntry) -> Self {
+        Self {
+            archive: Some(Box::new(ArchiveOverride {
+                entry,
+                extracted: OnceLock::new(),
+            })),
+            ..self
+        }
+    }
 
-            (
-                Vec::into_boxed_slice(os_str.encode_wide().collect()),
-                PathBuf::into_boxed_path(os_str.into()),
-            )
-        };
+    /// Whether this override can still be served, extracting it if it is an archive entry that
+    /// wasn't extracted yet.
+    fn is_available(&self) -> bool {
+        match &self.archive {
+            Some(archive) if self.hidden => archive.entry.archive_path().exists(),
+            Some(archive) => archive.extract().is_some(),
+            None => self.as_path().exists(),
+        }
+    }
 
+    /// The path this override is served from.
+    fn path(&self) -> &OverridePath {
+        self.archive
+            .as_ref()
+            .and_then(|archive| archive.extracted.get()?.as_ref())
+            .unwrap_or(&self.path)
+    }
+
+    fn with_package(self, package: Box<str>, roots: Box<[Box<str>]>) -> Self {
         Self {
-            display,
-            path_c_str,
-            wide_c_str,
+            package: Some(package),
+            roots,
+            ..self
         }
     }
 
-    pub fn as_str_lossy(&self) -> &str {
-        &self.display
+    /// Makes this override hide the asset instead of replacing it.
+    fn hiding(self) -> Self {
+        Self {
+            hidden: true,
+            ..self
+        }
     }
 
-    pub fn as_path(&self) -> &Path {
-        let bytes_with_nul = self.path_c_str.as_os_str().as_encoded_bytes();
-        let bytes_without_nul = &bytes_with_nul[..bytes_with_nul.len() - 1];
+    /// Whether the asset should be reported as absent instead of being served from this
+    /// override's path, which is the whiteout marker or package that hides it.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
 
-        // SAFETY: Source OsStr bytes split before valid substring ("\0"),
-        // which is always inserted by `VfsOverride::new`
-        unsafe { Path::new(OsStr::from_encoded_bytes_unchecked(bytes_without_nul)) }
+    /// ID of the package this override was discovered in.
+    pub fn package(&self) -> Option<&str> {
+        self.package.as_deref()
+    }
+
+    /// Mount roots this override is limited to, empty if it applies to every root.
+    pub fn roots(&self) -> impl Iterator<Item = &str> {
+        self.roots.iter().map(|root| &**root)
     }
 
-    pub fn as_wide(&self) -> &[u16] {
-        &self.wide_c_str[..self.wide_c_str.len() - 1]
+    /// Whether this override applies to paths under the mount `root`.
+    ///
+    /// Paths without a mount root, like disk paths, only use overrides that apply to every root.
+    pub fn applies_to(&self, root: Option<&str>) -> bool {
+        self.roots.is_empty() || root.is_some_and(|root| self.roots().any(|r| r == root))
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.roots.is_empty()
+            || other.roots.is_empty()
+            || self.roots().any(|root| other.applies_to(Some(root)))
+    }
+
+    pub fn as_str_lossy(&self) -> &str {
+        &self.display
+    }
+
+    /// The path this override is served from. Archive entries that weren't extracted yet have
+    /// their path inside the archive.
+    pub fn as_path(&self) -> &Path {
+        self.path().as_path()
     }
 
     pub fn as_c_str(&self) -> *const u8 {
-        self.path_c_str.as_os_str().as_encoded_bytes().as_ptr()
+        self.path().c_str.as_os_str().as_encoded_bytes().as_ptr()
     }
+}
 
-    pub fn as_wide_c_str(&self) -> *const u16 {
-        self.wide_c_str.as_ptr()
+impl OverridePath {
+    fn new(path: &Path) -> Self {
+        let mut os_str = path.as_os_str().to_os_string();
+        os_str.push("\0");
+
+        Self {
+            #[cfg(windows)]
+            wide_c_str: windows::encode_wide_c_str(&os_str),
+            c_str: PathBuf::into_boxed_path(os_str.into()),
+        }
     }
 
-    pub fn as_pcstr(&self) -> PCSTR {
-        PCSTR::from_raw(self.as_c_str())
+    fn as_path(&self) -> &Path {
+        let bytes_with_nul = self.c_str.as_os_str().as_encoded_bytes();
+        let bytes_without_nul = &bytes_with_nul[..bytes_with_nul.len() - 1];
+
+        // SAFETY: Source OsStr bytes split before valid substring ("\0"),
+        // which is always inserted by `OverridePath::new`
+        unsafe { Path::new(OsStr::from_encoded_bytes_unchecked(bytes_without_nul)) }
     }
+}
 
-    pub fn as_pcwstr(&self) -> PCWSTR {
-        PCWSTR::from_raw(self.as_wide_c_str())
+impl ArchiveOverride {
+    /// The extracted copy of the entry, extracting it the first time this is called. Returns
+    /// `None` if the entry couldn't be extracted, which is not retried.
+    fn extract(&self) -> Option<&OverridePath> {
+        self.extracted
+            .get_or_init(|| match self.entry.extract() {
+                Ok(path) => Some(OverridePath::new(&path)),
+                Err(e) => {
+                    error!(error = %e, entry = %self.entry.display_path().display(), "failed to extract archive entry");
+                    None
+                }
+            })
+            .as_ref()
     }
 }
 
@@ -207,6 +581,9 @@ impl fmt::Debug for VfsOverride {
         f.debug_struct("VfsOverride")
             .field("display", &self.display)
             .field("path", &self.as_path())
+            .field("package", &self.package)
+            .field("roots", &self.roots)
+            .field("hidden", &self.hidden)
             .finish()
     }
 }
@@ -223,150 +600,433 @@ impl AsRef<Path> for VfsOverride {
     }
 }
 
-impl AsRef<[u16]> for VfsOverride {
-    fn as_ref(&self) -> &[u16] {
-        self.as_wide()
-    }
-}
+#[cfg(test)]
+mod test {
+    use std::{
+        fs,
+        io::Write,
+        iter,
+        path::{Path, PathBuf},
+    };
+
+    use me3_mod_protocol::package::AssetOverrideSource;
+    use tempfile::TempDir;
+    use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+    use super::VfsOverrideMapping;
 
-impl From<&VfsOverride> for PCSTR {
-    fn from(value: &VfsOverride) -> Self {
-        value.as_pcstr()
+    /// A package with only the settings a test needs, the rest keep their defaults.
+    #[derive(Default)]
+    struct TestSource {
+        id: &'static str,
+        path: PathBuf,
+        roots: Vec<String>,
+        target: Option<&'static str>,
+        exclude: Vec<String>,
+        mount_point: Option<&'static str>,
+        hide: Vec<String>,
     }
-}
 
-impl From<&VfsOverride> for PCWSTR {
-    fn from(value: &VfsOverride) -> Self {
-        value.as_pcwstr()
+    impl TestSource {
+        fn new<P: Into<PathBuf>>(id: &'static str, path: P) -> Self {
+            Self {
+                id,
+                path: path.into(),
+                ..Default::default()
+            }
+        }
     }
-}
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
-struct VfsKey(Box<Path>);
+    impl AssetOverrideSource for TestSource {
+        fn asset_path(&self) -> &Path {
+            &self.path
+        }
 
-impl VfsKey {
-    /// Turns a disk path into an asset lookup key that includes the root directory.
-    fn for_disk_path<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
-        let normalized = path
-            .as_ref()
-            .normalize_virtually()?
-            .components()
-            .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
-            .collect();
+        fn source_id(&self) -> String {
+            self.id.to_owned()
+        }
+
+        fn asset_roots(&self) -> &[String] {
+            &self.roots
+        }
+
+        fn asset_target(&self) -> Option<&str> {
+            self.target
+        }
+
+        fn asset_exclude(&self) -> &[String] {
+            &self.exclude
+        }
+
+        fn asset_mount_point(&self) -> Option<&str> {
+            self.mount_point
+        }
 
-        Ok(Self(PathBuf::into_boxed_path(normalized)))
+        fn asset_hide(&self) -> &[String] {
+            &self.hide
+        }
     }
 
-    /// Turns a vfs path into an asset lookup key that does not include the root directory.
-    fn for_vfs_path<P: AsRef<Path>>(path: P) -> Self {
-        let normalized = path
-            .as_ref()
-            .components()
-            .skip_while(|c| matches!(c.as_os_str().as_encoded_bytes().last(), Some(b':')))
-            .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
-            .collect();
+    /// Creates a temporary directory containing empty `files`.
+    pub(super) fn fixture(files: &[&str]) -> TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        for file in files {
+            let path = temp_dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, []).unwrap();
+        }
 
-        Self(PathBuf::into_boxed_path(normalized))
+        temp_dir
     }
 
-    /// Turns a disk path into an asset lookup key that does not include the root directory.
-    fn for_asset_path<P: AsRef<Path>>(path: P, base: &Self) -> Result<Self, io::Error> {
-        Self::for_disk_path(path)?.strip_prefix(base)
+    #[test]
+    fn scan_directory_and_overrides() {
+        let mut asset_mapping = VfsOverrideMapping::new().unwrap();
+
+        let test_mod_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test-data/test-mod");
+        asset_mapping.scan_directory(test_mod_dir).unwrap();
+
+        assert!(
+            asset_mapping
+                .vfs_override("data0:/regulation.bin")
+                .is_some(),
+            "override for regulation.bin was not found"
+        );
+        assert!(
+            asset_mapping
+                .vfs_override("data0:/event/common.emevd.dcx")
+                .is_some(),
+            "override for event/common.emevd.dcx not found"
+        );
+        assert!(
+            asset_mapping
+                .vfs_override("data0:/common.emevd.dcx")
+                .is_none(),
+            "event/common.emevd.dcx was found incorrectly under the regulation root"
+        );
     }
 
-    /// Strips the root directory from a disk asset lookup key.
-    fn strip_prefix(&self, base: &Self) -> Result<Self, io::Error> {
-        let stripped = self
-            .0
-            .strip_prefix(base)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidFilename, e))?;
+    #[test]
+    fn keeps_shadowed_candidates() {
+        let temp_dir = fixture(&[
+            "first/parts/am_m_1000.partsbnd.dcx",
+            "second/parts/am_m_1000.partsbnd.dcx",
+        ]);
+        let second = temp_dir.path().join("second");
 
-        Ok(Self(stripped.into()))
+        let mut asset_mapping = VfsOverrideMapping::new().unwrap();
+        asset_mapping
+            .scan_directories(
+                [
+                    TestSource::new("first", temp_dir.path().join("first")),
+                    TestSource::new("second", &second),
+                ]
+                .into_iter(),
+            )
+            .unwrap();
+
+        let path = "data0:/parts/am_m_1000.partsbnd.dcx";
+
+        let candidates = asset_mapping.vfs_candidates(path).collect::<Vec<_>>();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].package(), Some("first"));
+        assert_eq!(asset_mapping.conflicts().count(), 1);
+
+        let report = asset_mapping.conflict_report(&[]);
+        assert_eq!(report[0].providers, ["first", "second"]);
+        assert_eq!(report[0].winner(), "second");
+        assert!(!report[0].resolved);
+
+        let served = asset_mapping.vfs_override(path).unwrap();
+        assert_eq!(served.package(), Some("second"));
+
+        fs::remove_file(second.join("parts/am_m_1000.partsbnd.dcx")).unwrap();
+
+        let served = asset_mapping.vfs_override(path).unwrap();
+        assert_eq!(served.package(), Some("first"));
     }
-}
 
-impl AsRef<Path> for VfsKey {
-    fn as_ref(&self) -> &Path {
-        &self.0
+    #[test]
+    fn root_specific_overrides() {
+        let temp_dir = fixture(&[
+            "base/parts/am_m_1000.partsbnd.dcx",
+            "dlc/@dlc02/parts/am_m_1000.partsbnd.dcx",
+            "limited/msg/engus/item.msgbnd.dcx",
+        ]);
+        let dir = |name| temp_dir.path().join(name);
+
+        let mut asset_mapping = VfsOverrideMapping::new().unwrap();
+        asset_mapping
+            .scan_directories(
+                [
+                    TestSource::new("base", dir("base")),
+                    TestSource::new("dlc", dir("dlc")),
+                    TestSource {
+                        roots: vec!["DLC01:".to_owned()],
+                        ..TestSource::new("limited", dir("limited"))
+                    },
+                ]
+                .into_iter(),
+            )
+            .unwrap();
+
+        let served = |path| asset_mapping.vfs_override(path).and_then(|o| o.package());
+
+        assert_eq!(served("data0:/parts/am_m_1000.partsbnd.dcx"), Some("base"));
+        assert_eq!(served("dlc02:/parts/am_m_1000.partsbnd.dcx"), Some("dlc"));
+        assert_eq!(served("dlc01:/msg/engus/item.msgbnd.dcx"), Some("limited"));
+        assert_eq!(served("data0:/msg/engus/item.msgbnd.dcx"), None);
+
+        let report = asset_mapping.conflict_report(&[]);
+        assert_eq!(report.len(), 1);
+        assert_eq!(
+            report[0].path,
+            Path::new("dlc02:").join("parts/am_m_1000.partsbnd.dcx")
+        );
+        assert_eq!(report[0].providers, ["base", "dlc"]);
     }
-}
 
-impl Borrow<Path> for VfsKey {
-    fn borrow(&self) -> &Path {
-        &self.0
+    #[test]
+    fn single_file_mappings() {
+        let temp_dir = fixture(&["package/regulation.bin", "variants/hard.bin"]);
+        let package = temp_dir.path().join("package");
+        let variant = temp_dir.path().join("variants/hard.bin");
+
+        let mut asset_mapping = VfsOverrideMapping::new().unwrap();
+        asset_mapping
+            .scan_directories(iter::once(TestSource::new("package", &package)))
+            .unwrap();
+        asset_mapping
+            .scan_directories(iter::once(TestSource {
+                target: Some("data0:/regulation.bin"),
+                ..TestSource::new("hard", &variant)
+            }))
+            .unwrap();
+
+        let served = asset_mapping.vfs_override("data0:/regulation.bin").unwrap();
+        assert_eq!(served.package(), Some("hard"));
+        assert_eq!(served.as_path(), variant);
+
+        let served = asset_mapping.vfs_override("data1:/regulation.bin").unwrap();
+        assert_eq!(served.package(), Some("package"));
+
+        let report = asset_mapping.conflict_report(&[]);
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].providers, ["package", "hard"]);
+
+        assert!(asset_mapping
+            .scan_directories(iter::once(TestSource {
+                target: Some("regulation.bin"),
+                ..TestSource::new("dir", &package)
+            }))
+            .is_err());
     }
-}
 
-#[cfg(test)]
-mod test {
-    use std::path::Path;
+    #[test]
+    fn filtered_and_mounted_packages() {
+        let temp_dir = fixture(&[
+            "c0000.anibnd.dcx",
+            "c0000.chrbnd.dcx",
+            "README.md",
+            "src/c0000.psd",
+            ".git/HEAD",
+        ]);
 
-    use super::{VfsKey, VfsOverrideMapping};
+        let mut asset_mapping = VfsOverrideMapping::new().unwrap();
+        asset_mapping
+            .scan_directories(iter::once(TestSource {
+                mount_point: Some("dlc02:/chr"),
+                exclude: vec!["*.chrbnd.dcx".to_owned()],
+                ..TestSource::new("mounted", temp_dir.path())
+            }))
+            .unwrap();
+
+        let served = |path| asset_mapping.vfs_override(path).is_some();
+
+        assert!(served("dlc02:/chr/c0000.anibnd.dcx"));
+        assert!(!served("data0:/chr/c0000.anibnd.dcx"));
+        assert!(!served("dlc02:/c0000.anibnd.dcx"));
+        assert!(!served("dlc02:/chr/c0000.chrbnd.dcx"));
+        assert!(!served("dlc02:/chr/readme.md"));
+        assert!(!served("dlc02:/chr/src/c0000.psd"));
+        assert!(!served("dlc02:/chr/.git/head"));
+    }
 
     #[test]
-    fn asset_path_lookup_keys() {
-        const FAKE_MOD_BASE: &str = "D:/ModBase";
-        let base_path = VfsKey::for_disk_path(Path::new(FAKE_MOD_BASE)).unwrap();
+    fn hidden_assets_follow_load_order() {
+        let temp_dir = fixture(&[
+            "base/parts/a.dcx",
+            "base/parts/b.dcx",
+            "hider/parts/a.dcx.me3-hide",
+            "restorer/parts/a.dcx",
+        ]);
+        let dir = |name| temp_dir.path().join(name);
+
+        let mut asset_mapping = VfsOverrideMapping::new().unwrap();
+        asset_mapping
+            .scan_directories(iter::once(TestSource::new("base", dir("base"))))
+            .unwrap();
+        asset_mapping
+            .scan_directories(iter::once(TestSource {
+                hide: vec!["data0:/parts/b.dcx".to_owned(), "sfx/c.dcx".to_owned()],
+                ..TestSource::new("hider", dir("hider"))
+            }))
+            .unwrap();
+
+        let hidden = |mapping: &VfsOverrideMapping, path| {
+            mapping
+                .vfs_override(path)
+                .map(|o| (o.package().unwrap().to_owned(), o.is_hidden()))
+        };
 
         assert_eq!(
-            VfsKey::for_asset_path(
-                Path::new(&format!(
-                    "{FAKE_MOD_BASE}/parts/aet/aet007/aet007_071.tpf.dcx"
-                )),
-                &base_path
-            )
-            .unwrap()
-            .as_ref(),
-            Path::new("parts/aet/aet007/aet007_071.tpf.dcx"),
+            hidden(&asset_mapping, "data0:/parts/a.dcx"),
+            Some(("hider".to_owned(), true))
+        );
+        assert_eq!(
+            hidden(&asset_mapping, "data0:/parts/b.dcx"),
+            Some(("hider".to_owned(), true))
+        );
+        assert_eq!(
+            hidden(&asset_mapping, "dlc02:/parts/b.dcx"),
+            Some(("base".to_owned(), false))
+        );
+        assert_eq!(
+            hidden(&asset_mapping, "dlc02:/sfx/c.dcx"),
+            Some(("hider".to_owned(), true))
         );
 
+        asset_mapping
+            .scan_directories(iter::once(TestSource::new("restorer", dir("restorer"))))
+            .unwrap();
+
         assert_eq!(
-            VfsKey::for_asset_path(
-                Path::new(&format!(
-                    "{FAKE_MOD_BASE}/hkxbnd/m60_42_36_00/h60_42_36_00_423601.hkx.dcx"
-                )),
-                &base_path
-            )
-            .unwrap()
-            .as_ref(),
-            Path::new("hkxbnd/m60_42_36_00/h60_42_36_00_423601.hkx.dcx"),
+            hidden(&asset_mapping, "data0:/parts/a.dcx"),
+            Some(("restorer".to_owned(), false))
         );
 
+        fs::remove_file(dir("hider").join("parts/a.dcx.me3-hide")).unwrap();
+        fs::remove_dir_all(dir("restorer")).unwrap();
+
         assert_eq!(
-            VfsKey::for_asset_path(
-                Path::new(&format!("{FAKE_MOD_BASE}/regulation.bin")),
-                &base_path
-            )
-            .unwrap()
-            .as_ref(),
-            Path::new("regulation.bin"),
+            hidden(&asset_mapping, "data0:/parts/a.dcx"),
+            Some(("base".to_owned(), false))
         );
     }
 
     #[test]
-    fn scan_directory_and_overrides() {
+    fn archive_packages_extract_on_open() {
+        let temp_dir = fixture(&["directory/msg/item.msgbnd.dcx"]);
+        let archive_path = temp_dir.path().join("package.zip");
+        let cache_dir = temp_dir.path().join("cache");
+
+        let mut zip = ZipWriter::new(fs::File::create(&archive_path).unwrap());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+        zip.add_directory("Parts/", options).unwrap();
+        zip.start_file("Parts/am_m_1000.partsbnd.dcx", options)
+            .unwrap();
+        zip.write_all(b"archived").unwrap();
+        zip.start_file("regulation.bin", options).unwrap();
+        zip.finish().unwrap();
+
         let mut asset_mapping = VfsOverrideMapping::new().unwrap();
+        asset_mapping.set_cache_dir(&cache_dir);
+        asset_mapping
+            .scan_directories(
+                [
+                    TestSource::new("archive", &archive_path),
+                    TestSource::new("directory", temp_dir.path().join("directory")),
+                ]
+                .into_iter(),
+            )
+            .unwrap();
 
-        let test_mod_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test-data/test-mod");
-        asset_mapping.scan_directory(test_mod_dir).unwrap();
+        let path = "data0:/parts/am_m_1000.partsbnd.dcx";
 
-        assert!(
+        let candidate = asset_mapping.vfs_candidates(path).next().unwrap();
+        assert_eq!(candidate.package(), Some("archive"));
+        assert!(!cache_dir.exists(), "entry was extracted early");
+
+        let served = asset_mapping.vfs_override(path).unwrap();
+        assert!(served.as_path().starts_with(cache_dir.join("packages")));
+        assert_eq!(fs::read(served.as_path()).unwrap(), b"archived");
+        assert!(served.to_string().ends_with("am_m_1000.partsbnd.dcx"));
+
+        assert!(asset_mapping
+            .vfs_override("data0:/regulation.bin")
+            .is_some());
+        assert!(asset_mapping.vfs_override("data0:/parts/").is_none());
+        assert_eq!(
             asset_mapping
-                .vfs_override("data0:/regulation.bin")
-                .is_some(),
-            "override for regulation.bin was not found"
+                .vfs_override("data0:/msg/item.msgbnd.dcx")
+                .and_then(|o| o.package()),
+            Some("directory")
         );
-        assert!(
+    }
+
+    #[test]
+    fn identical_archive_entries_share_a_cached_copy() {
+        let temp_dir = fixture(&[]);
+        let cache_dir = temp_dir.path().join("cache");
+
+        for (name, contents) in [("a.zip", "same"), ("b.zip", "same"), ("c.zip", "other")] {
+            let mut zip = ZipWriter::new(fs::File::create(temp_dir.path().join(name)).unwrap());
+            zip.start_file("regulation.bin", SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+            zip.finish().unwrap();
+        }
+
+        let served = |name: &'static str| {
+            let mut asset_mapping = VfsOverrideMapping::new().unwrap();
+            asset_mapping.set_cache_dir(&cache_dir);
             asset_mapping
-                .vfs_override("data0:/event/common.emevd.dcx")
-                .is_some(),
-            "override for event/common.emevd.dcx not found"
-        );
-        assert!(
+                .scan_directories([TestSource::new(name, temp_dir.path().join(name))].into_iter())
+                .unwrap();
+
             asset_mapping
-                .vfs_override("data0:/common.emevd.dcx")
-                .is_none(),
-            "event/common.emevd.dcx was found incorrectly under the regulation root"
+                .vfs_override("data0:/regulation.bin")
+                .unwrap()
+                .as_path()
+                .to_owned()
+        };
+
+        assert_eq!(served("a.zip"), served("b.zip"));
+        assert_ne!(served("a.zip"), served("c.zip"));
+        assert_eq!(fs::read_dir(cache_dir.join("packages")).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn failed_archive_entries_fall_through() {
+        let temp_dir = fixture(&["directory/regulation.bin", "cache"]);
+        let archive_path = temp_dir.path().join("package.zip");
+
+        let mut zip = ZipWriter::new(fs::File::create(&archive_path).unwrap());
+        zip.start_file("regulation.bin", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"archived").unwrap();
+        zip.finish().unwrap();
+
+        // The cache directory is taken by a file, so nothing can be extracted.
+        let mut asset_mapping = VfsOverrideMapping::new().unwrap();
+        asset_mapping.set_cache_dir(temp_dir.path().join("cache"));
+        asset_mapping
+            .scan_directories(
+                [
+                    TestSource::new("directory", temp_dir.path().join("directory")),
+                    TestSource::new("archive", &archive_path),
+                ]
+                .into_iter(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            asset_mapping
+                .vfs_override("data0:/regulation.bin")
+                .and_then(|o| o.package()),
+            Some("directory")
         );
     }
 }
@@ -0,0 +1,167 @@
+//! Packages distributed as zip archives. Entries are indexed when the package is scanned and only
+//! extracted into a content-addressed cache when they are first opened.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tracing::debug;
+use xxhash_rust::xxh3::Xxh3Default;
+use zip::{result::ZipResult, ZipArchive};
+
+pub(super) struct Archive {
+    path: Box<Path>,
+    /// Directory that entries are extracted to.
+    cache_dir: Box<Path>,
+    /// Entries are extracted from whichever thread opens them first.
+    zip: Mutex<ZipArchive<File>>,
+}
+
+/// A file in an archive.
+pub(super) struct ArchiveFile {
+    pub(super) index: usize,
+    pub(super) path: PathBuf,
+}
+
+pub(super) struct ArchiveEntry {
+    archive: Arc<Archive>,
+    index: usize,
+    name: PathBuf,
+}
+
+/// Whether the package source at `path` is a zip archive rather than a directory.
+pub(super) fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+        && path.is_file()
+}
+
+impl Archive {
+    pub(super) fn open(path: &Path, cache_dir: &Path) -> ZipResult<Arc<Self>> {
+        let zip = ZipArchive::new(File::open(path)?)?;
+
+        Ok(Arc::new(Self {
+            path: path.into(),
+            cache_dir: cache_dir.into(),
+            zip: Mutex::new(zip),
+        }))
+    }
+
+    /// All files in the archive, skipping directories and entries with unsafe paths.
+    pub(super) fn files(&self) -> ZipResult<Vec<ArchiveFile>> {
+        let mut zip = self.zip.lock().unwrap();
+        let mut files = Vec::with_capacity(zip.len());
+
+        for index in 0..zip.len() {
+            let file = zip.by_index_raw(index)?;
+
+            let Some(path) = file.enclosed_name().filter(|_| !file.is_dir()) else {
+                continue;
+            };
+
+            files.push(ArchiveFile { index, path });
+        }
+
+        Ok(files)
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl ArchiveEntry {
+    pub(super) fn new(archive: Arc<Archive>, file: &ArchiveFile) -> Self {
+        Self {
+            archive,
+            index: file.index,
+            name: file.path.clone(),
+        }
+    }
+
+    pub(super) fn archive_path(&self) -> &Path {
+        self.archive.path()
+    }
+
+    /// The path of the entry inside its archive, for display purposes.
+    pub(super) fn display_path(&self) -> PathBuf {
+        self.archive.path().join(&self.name)
+    }
+
+    /// Extracts the entry into the cache, unless a copy with the same contents is cached already,
+    /// and returns the path of the copy. Copies are named after a hash of their contents, so
+    /// entries with the same contents share one copy.
+    pub(super) fn extract(&self) -> io::Result<PathBuf> {
+        let mut zip = self.archive.zip.lock().unwrap();
+
+        // Hashing the entry before extracting it avoids writing entries that are cached already.
+        // Reading the whole entry also verifies its checksum.
+        let mut hasher = Xxh3Default::new();
+        let mut entry = zip.by_index(self.index)?;
+        let mut buffer = vec![0; 64 * 1024];
+
+        loop {
+            match entry.read(&mut buffer)? {
+                0 => break,
+                read => hasher.update(&buffer[..read]),
+            }
+        }
+
+        drop(entry);
+
+        let mut cache_name = format!("{:032x}", hasher.digest128());
+
+        if let Some(extension) = self.name.extension().and_then(|e| e.to_str()) {
+            cache_name.push('.');
+            cache_name.push_str(extension);
+        }
+
+        let path = self.archive.cache_dir.join(cache_name);
+
+        if path.is_file() {
+            debug!(path = %path.display(), "using cached archive entry");
+            return Ok(path);
+        }
+
+        self.try_extract(&mut zip, &path)?;
+
+        debug!(entry = %self.display_path().display(), path = %path.display(), "extracted archive entry");
+        Ok(path)
+    }
+
+    fn try_extract(&self, zip: &mut ZipArchive<File>, path: &Path) -> io::Result<()> {
+        // Entries with the same contents may be extracted concurrently from different archives.
+        static PARTIAL_ID: AtomicUsize = AtomicUsize::new(0);
+
+        fs::create_dir_all(&self.archive.cache_dir)?;
+
+        let mut partial_path = path.as_os_str().to_owned();
+        partial_path.push(format!(
+            ".{}-{}.part",
+            std::process::id(),
+            PARTIAL_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = (|| {
+            let mut file = zip.by_index(self.index)?;
+            let mut partial = File::create(&partial_path)?;
+
+            io::copy(&mut file, &mut partial)?;
+            partial.sync_all()?;
+
+            fs::rename(&partial_path, path)
+        })();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&partial_path);
+        }
+
+        result
+    }
+}
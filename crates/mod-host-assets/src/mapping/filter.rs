@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use me3_mod_protocol::package::DEFAULT_EXCLUDE;
+
+/// Decides which files of a package are mapped, based on its `include` and `exclude` patterns.
+pub(super) struct AssetFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    default_exclude: GlobSet,
+}
+
+impl AssetFilter {
+    pub(super) fn new<S: AsRef<str>>(include: &[S], exclude: &[S]) -> Result<Self, globset::Error> {
+        let include = (!include.is_empty())
+            .then(|| glob_set(include.iter().map(AsRef::as_ref)))
+            .transpose()?;
+
+        Ok(Self {
+            include,
+            exclude: glob_set(exclude.iter().map(AsRef::as_ref))?,
+            default_exclude: glob_set(DEFAULT_EXCLUDE.iter().copied())?,
+        })
+    }
+
+    /// Whether the file at `path`, relative to the package source, should be mapped.
+    ///
+    /// Files matching the default exclude patterns are only mapped if an `include` pattern
+    /// matches them.
+    pub(super) fn is_match(&self, path: &Path) -> bool {
+        if self.exclude.is_match(path) {
+            return false;
+        }
+
+        match &self.include {
+            Some(include) => include.is_match(path),
+            None => !self.default_exclude.is_match(path),
+        }
+    }
+}
+
+fn glob_set<'a>(patterns: impl Iterator<Item = &'a str>) -> Result<GlobSet, globset::Error> {
+    patterns
+        .map(glob)
+        .try_fold(GlobSetBuilder::new(), |mut builder, glob| {
+            builder.add(glob?);
+            Ok(builder)
+        })?
+        .build()
+}
+
+/// Asset lookups are case insensitive, so patterns are too.
+fn glob(pattern: &str) -> Result<Glob, globset::Error> {
+    GlobBuilder::new(pattern)
+        .case_insensitive(true)
+        .literal_separator(true)
+        .backslash_escape(false)
+        .build()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::AssetFilter;
+
+    #[test]
+    fn default_excludes_can_be_included() {
+        let filter = AssetFilter::new::<&str>(&[], &["**/*.wav"]).unwrap();
+
+        assert!(filter.is_match(Path::new("chr/c0000.anibnd.dcx")));
+        assert!(!filter.is_match(Path::new("README.md")));
+        assert!(!filter.is_match(Path::new(".git/objects/ab/cdef")));
+        assert!(!filter.is_match(Path::new("sd/source/c0000.psd")));
+        assert!(!filter.is_match(Path::new("sound/voice.wav")));
+
+        let filter = AssetFilter::new(&["chr/**", "**/*.md"], &[]).unwrap();
+
+        assert!(filter.is_match(Path::new("chr/c0000.anibnd.dcx")));
+        assert!(filter.is_match(Path::new("docs/notes.md")));
+        assert!(!filter.is_match(Path::new("parts/am_m_1000.partsbnd.dcx")));
+    }
+}
@@ -0,0 +1,296 @@
+//! Persisted results of scanning package directories. An index is reused for as long as none of
+//! the directories it was created from were modified since, which only takes a `stat` of each
+//! directory instead of reading all of their entries.
+
+use std::{
+    fs::{self, File},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bincode::{Decode, Encode};
+use rayon::iter::{IntoParallelRefIterator, ParallelBridge, ParallelIterator};
+use thiserror::Error;
+
+use super::key::VfsKey;
+
+/// Incremented whenever the layout of [`ScanIndex`] changes.
+const INDEX_VERSION: u32 = 1;
+
+/// Directories modified this recently may be modified again without their modification time
+/// changing, so indexes containing them are not persisted.
+const MODIFIED_TIME_GRANULARITY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Decode, Encode)]
+pub(super) struct ScanIndex {
+    version: u32,
+    /// Lookup key of the package directory, i.e. its normalized path.
+    source: PathBuf,
+    directories: Vec<IndexedDirectory>,
+    files: Vec<IndexedFile>,
+}
+
+#[derive(Debug, Decode, Encode)]
+struct IndexedDirectory {
+    /// Path relative to the package directory.
+    path: PathBuf,
+    modified: SystemTime,
+}
+
+#[derive(Debug, Decode, Encode)]
+pub(super) struct IndexedFile {
+    /// Asset lookup key, relative to the package directory.
+    key: PathBuf,
+    /// Path relative to the package directory.
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+enum IndexEntry {
+    Directory(IndexedDirectory),
+    File(IndexedFile),
+}
+
+/// Reason a persisted index can't be used for a package.
+#[derive(Debug, Error)]
+pub(super) enum StaleIndex {
+    #[error("no index was found")]
+    Missing,
+
+    #[error("the index could not be read: {0}")]
+    Unreadable(#[from] io::Error),
+
+    #[error("the index is corrupt: {0}")]
+    Corrupt(#[from] bincode::error::DecodeError),
+
+    #[error("the index was created by a different version of me3")]
+    Version,
+
+    #[error("the index belongs to a different package")]
+    Source,
+
+    #[error("{0} was modified")]
+    Modified(PathBuf),
+
+    #[error("a rescan was requested")]
+    Rescan,
+}
+
+impl ScanIndex {
+    /// Scans the package directory at `source`, recording every file in it.
+    pub(super) fn scan(source: &Path) -> io::Result<Self> {
+        let root_key = VfsKey::for_disk_path(source)?;
+
+        let mut directories = Vec::new();
+        let mut files = Vec::new();
+
+        for entry in scan_directory(source, source, &root_key) {
+            match entry? {
+                IndexEntry::Directory(directory) => directories.push(directory),
+                IndexEntry::File(file) => files.push(file),
+            }
+        }
+
+        Ok(Self {
+            version: INDEX_VERSION,
+            source: root_key.0.into(),
+            directories,
+            files,
+        })
+    }
+
+    /// Loads the index persisted at `path` for the package directory at `source`, if it is still
+    /// up to date.
+    pub(super) fn load(path: &Path, source: &Path) -> Result<Self, StaleIndex> {
+        let mut reader = match File::open(path) {
+            Ok(file) => BufReader::new(file),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(StaleIndex::Missing),
+            Err(e) => return Err(StaleIndex::Unreadable(e)),
+        };
+
+        let index: Self = bincode::decode_from_std_read(&mut reader, bincode::config::standard())?;
+
+        if index.version != INDEX_VERSION {
+            return Err(StaleIndex::Version);
+        }
+
+        match VfsKey::for_disk_path(source) {
+            Ok(root_key) if *root_key.0 == *index.source => {}
+            _ => return Err(StaleIndex::Source),
+        }
+
+        // Adding, removing or renaming an entry updates the modification time of its directory.
+        let modified = index.directories.par_iter().find_any(|directory| {
+            !fs::metadata(source.join(&directory.path))
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified == directory.modified)
+        });
+
+        match modified {
+            Some(directory) => Err(StaleIndex::Modified(source.join(&directory.path))),
+            None => Ok(index),
+        }
+    }
+
+    /// Persists the index at `path`, unless a directory was modified too recently to tell whether
+    /// it changes again later.
+    pub(super) fn save(&self, path: &Path) -> io::Result<bool> {
+        let now = SystemTime::now();
+
+        let is_recent = |modified: SystemTime| {
+            now.duration_since(modified)
+                .is_ok_and(|elapsed| elapsed < MODIFIED_TIME_GRANULARITY)
+        };
+
+        if self.directories.iter().any(|d| is_recent(d.modified)) {
+            return Ok(false);
+        }
+
+        self.write(path).map(|_| true)
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut partial_path = path.as_os_str().to_owned();
+        partial_path.push(format!(".{}.part", std::process::id()));
+
+        let result = (|| {
+            let mut writer = BufWriter::new(File::create(&partial_path)?);
+
+            bincode::encode_into_std_write(self, &mut writer, bincode::config::standard())
+                .map_err(io::Error::other)?;
+
+            writer.into_inner()?.sync_all()?;
+
+            fs::rename(&partial_path, path)
+        })();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&partial_path);
+        }
+
+        result
+    }
+
+    /// The file name of the index for the package directory at `source`.
+    pub(super) fn file_name(source: &Path) -> io::Result<String> {
+        let mut hasher = DefaultHasher::new();
+        VfsKey::for_disk_path(source)?.hash(&mut hasher);
+
+        Ok(format!("{:016x}.index", hasher.finish()))
+    }
+
+    pub(super) fn files(&self) -> &[IndexedFile] {
+        &self.files
+    }
+}
+
+impl IndexedFile {
+    /// The asset lookup key of this file, relative to the package directory.
+    pub(super) fn key(&self) -> VfsKey {
+        VfsKey(self.key.as_path().into())
+    }
+
+    /// The path of this file, relative to the package directory.
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn scan_directory(source: &Path, dir: &Path, root_key: &VfsKey) -> Vec<io::Result<IndexEntry>> {
+    // Reading the modification time before the entries makes sure changes made while scanning
+    // invalidate the index.
+    let modified = match fs::metadata(dir).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => modified,
+        Err(e) => return vec![Err(e)],
+    };
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => return vec![Err(e)],
+    };
+
+    let mut result = entries
+        .flatten()
+        .par_bridge()
+        .flat_map_iter(|dir_entry| match dir_entry.file_type() {
+            Ok(file_type)
+                if file_type.is_dir() || (file_type.is_symlink() && dir_entry.path().is_dir()) =>
+            {
+                scan_directory(source, &dir_entry.path(), root_key)
+            }
+            Ok(_) => vec![index_file(source, &dir_entry.path(), root_key)],
+            Err(e) => vec![Err(e)],
+        })
+        .collect::<Vec<_>>();
+
+    result.push(Ok(IndexEntry::Directory(IndexedDirectory {
+        path: relative_path(source, dir),
+        modified,
+    })));
+
+    result
+}
+
+fn index_file(source: &Path, path: &Path, root_key: &VfsKey) -> io::Result<IndexEntry> {
+    let key = VfsKey::for_asset_path(path, root_key)?;
+    let metadata = fs::symlink_metadata(path)?;
+
+    Ok(IndexEntry::File(IndexedFile {
+        key: key.0.into(),
+        path: relative_path(source, path),
+        size: metadata.len(),
+        modified: metadata.modified()?,
+    }))
+}
+
+fn relative_path(source: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(source).unwrap_or(path).to_path_buf()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{ScanIndex, StaleIndex};
+    use crate::mapping::test::fixture;
+
+    #[test]
+    fn index_is_invalidated_by_modified_directories() {
+        let temp_dir = fixture(&["package/parts/a.dcx", "package/regulation.bin"]);
+        let package = temp_dir.path().join("package");
+        let index_path = temp_dir.path().join("index/package.index");
+
+        assert!(matches!(
+            ScanIndex::load(&index_path, &package),
+            Err(StaleIndex::Missing)
+        ));
+
+        let index = ScanIndex::scan(&package).unwrap();
+        assert_eq!(index.files().len(), 2);
+        assert!(!index.save(&index_path).unwrap(), "saved a racy index");
+
+        index.write(&index_path).unwrap();
+
+        let index = ScanIndex::load(&index_path, &package).unwrap();
+        assert_eq!(index.files().len(), 2);
+
+        assert!(matches!(
+            ScanIndex::load(&index_path, &temp_dir.path().join("other")),
+            Err(StaleIndex::Source)
+        ));
+
+        fs::remove_dir_all(package.join("parts")).unwrap();
+
+        assert!(matches!(
+            ScanIndex::load(&index_path, &package),
+            Err(StaleIndex::Modified(path)) if path.ends_with("parts")
+        ));
+    }
+}
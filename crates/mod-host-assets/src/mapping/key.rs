@@ -0,0 +1,208 @@
+use std::{
+    borrow::Borrow,
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct VfsKey(pub(super) Box<Path>);
+
+impl VfsKey {
+    /// Turns a disk path into an asset lookup key that includes the root directory.
+    pub(super) fn for_disk_path<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let normalized = normalize_virtually(path.as_ref())?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+            .collect();
+
+        Ok(Self(PathBuf::into_boxed_path(normalized)))
+    }
+
+    /// Turns a vfs path into an asset lookup key that does not include the root directory.
+    pub(super) fn for_vfs_path<P: AsRef<Path>>(path: P) -> Self {
+        let normalized = path
+            .as_ref()
+            .components()
+            .skip_while(|c| {
+                matches!(c, Component::RootDir)
+                    || matches!(c.as_os_str().as_encoded_bytes().last(), Some(b':'))
+            })
+            .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+            .collect();
+
+        Self(PathBuf::into_boxed_path(normalized))
+    }
+
+    /// Turns a disk path into an asset lookup key that does not include the root directory.
+    pub(super) fn for_asset_path<P: AsRef<Path>>(path: P, base: &Self) -> Result<Self, io::Error> {
+        Self::for_disk_path(path)?.strip_prefix(base)
+    }
+
+    /// Gets the mount root of a vfs path, e.g. `data0` for `data0:/regulation.bin`.
+    pub(super) fn vfs_root<P: AsRef<Path>>(path: P) -> Option<Box<str>> {
+        let root = path.as_ref().components().next()?.as_os_str().to_str()?;
+
+        root.strip_suffix(':').map(normalize_root)
+    }
+
+    /// Splits a top-level `@<root>` directory off an asset lookup key, returning the root that the
+    /// rest of the key applies to.
+    pub(super) fn split_root(self) -> (Option<Box<str>>, Self) {
+        let mut components = self.0.components();
+
+        let root = components
+            .next()
+            .and_then(|first| first.as_os_str().to_str()?.strip_prefix('@'))
+            .filter(|root| !root.is_empty())
+            .map(normalize_root);
+
+        match root {
+            Some(root) => (Some(root), Self(components.as_path().into())),
+            None => (None, self),
+        }
+    }
+
+    /// Strips `suffix` from the file name of this key, if it ends with it.
+    pub(super) fn strip_suffix(&self, suffix: &str) -> Option<Self> {
+        let stripped = self.0.to_str()?.strip_suffix(suffix)?;
+
+        Some(Self(Path::new(stripped).into()))
+    }
+
+    /// Appends `key` to this key, e.g. to place an asset under a mount point.
+    pub(super) fn join(&self, key: &Self) -> Self {
+        Self(self.0.join(&key.0).into_boxed_path())
+    }
+
+    /// Strips the root directory from a disk asset lookup key.
+    fn strip_prefix(&self, base: &Self) -> Result<Self, io::Error> {
+        let stripped = self
+            .0
+            .strip_prefix(base)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidFilename, e))?;
+
+        Ok(Self(stripped.into()))
+    }
+}
+
+impl AsRef<Path> for VfsKey {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Borrow<Path> for VfsKey {
+    fn borrow(&self) -> &Path {
+        &self.0
+    }
+}
+
+/// Normalizes the name of a VFS mount root, accepting forms like `dlc02`, `DLC02:` or `dlc02:/`.
+pub(super) fn normalize_root(root: &str) -> Box<str> {
+    root.trim_end_matches(['/', '\\'])
+        .trim_end_matches(':')
+        .to_lowercase()
+        .into_boxed_str()
+}
+
+/// Makes a path absolute and resolves `.` and `..` components without accessing the filesystem.
+#[cfg(windows)]
+fn normalize_virtually(path: &Path) -> Result<PathBuf, io::Error> {
+    use normpath::PathExt;
+
+    path.normalize_virtually().map(|path| path.into_path_buf())
+}
+
+/// Makes a path absolute and resolves `.` and `..` components without accessing the filesystem.
+#[cfg(not(windows))]
+fn normalize_virtually(path: &Path) -> Result<PathBuf, io::Error> {
+    use std::{env, path::Component};
+
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir()?.join(path)
+    };
+
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::VfsKey;
+
+    #[test]
+    fn asset_path_lookup_keys() {
+        const FAKE_MOD_BASE: &str = "D:/ModBase";
+        let base_path = VfsKey::for_disk_path(Path::new(FAKE_MOD_BASE)).unwrap();
+
+        assert_eq!(
+            VfsKey::for_asset_path(
+                Path::new(&format!(
+                    "{FAKE_MOD_BASE}/parts/aet/aet007/aet007_071.tpf.dcx"
+                )),
+                &base_path
+            )
+            .unwrap()
+            .as_ref(),
+            Path::new("parts/aet/aet007/aet007_071.tpf.dcx"),
+        );
+
+        assert_eq!(
+            VfsKey::for_asset_path(
+                Path::new(&format!(
+                    "{FAKE_MOD_BASE}/hkxbnd/m60_42_36_00/h60_42_36_00_423601.hkx.dcx"
+                )),
+                &base_path
+            )
+            .unwrap()
+            .as_ref(),
+            Path::new("hkxbnd/m60_42_36_00/h60_42_36_00_423601.hkx.dcx"),
+        );
+
+        assert_eq!(
+            VfsKey::for_asset_path(
+                Path::new(&format!("{FAKE_MOD_BASE}/regulation.bin")),
+                &base_path
+            )
+            .unwrap()
+            .as_ref(),
+            Path::new("regulation.bin"),
+        );
+    }
+
+    #[test]
+    fn root_specific_keys() {
+        assert_eq!(
+            VfsKey::vfs_root(Path::new("DLC02:/parts/am_m_1000.partsbnd.dcx")).as_deref(),
+            Some("dlc02")
+        );
+        assert_eq!(
+            VfsKey::vfs_root(Path::new("parts/am_m_1000.partsbnd.dcx")),
+            None
+        );
+
+        let (root, key) =
+            VfsKey(Path::new("@dlc02/parts/am_m_1000.partsbnd.dcx").into()).split_root();
+        assert_eq!(root.as_deref(), Some("dlc02"));
+        assert_eq!(key.as_ref(), Path::new("parts/am_m_1000.partsbnd.dcx"));
+
+        let (root, key) = VfsKey(Path::new("parts/am_m_1000.partsbnd.dcx").into()).split_root();
+        assert_eq!(root, None);
+        assert_eq!(key.as_ref(), Path::new("parts/am_m_1000.partsbnd.dcx"));
+    }
+}
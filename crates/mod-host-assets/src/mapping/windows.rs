@@ -0,0 +1,47 @@
+use std::{ffi::OsStr, os::windows::ffi::OsStrExt};
+
+use windows::core::{PCSTR, PCWSTR};
+
+use super::VfsOverride;
+
+/// Encodes a nul-terminated path as UTF-16.
+pub(super) fn encode_wide_c_str(os_str: &OsStr) -> Box<[u16]> {
+    os_str.encode_wide().collect()
+}
+
+impl VfsOverride {
+    pub fn as_wide(&self) -> &[u16] {
+        let wide_c_str = &self.path().wide_c_str;
+        &wide_c_str[..wide_c_str.len() - 1]
+    }
+
+    pub fn as_wide_c_str(&self) -> *const u16 {
+        self.path().wide_c_str.as_ptr()
+    }
+
+    pub fn as_pcstr(&self) -> PCSTR {
+        PCSTR::from_raw(self.as_c_str())
+    }
+
+    pub fn as_pcwstr(&self) -> PCWSTR {
+        PCWSTR::from_raw(self.as_wide_c_str())
+    }
+}
+
+impl AsRef<[u16]> for VfsOverride {
+    fn as_ref(&self) -> &[u16] {
+        self.as_wide()
+    }
+}
+
+impl From<&VfsOverride> for PCSTR {
+    fn from(value: &VfsOverride) -> Self {
+        value.as_pcstr()
+    }
+}
+
+impl From<&VfsOverride> for PCWSTR {
+    fn from(value: &VfsOverride) -> Self {
+        value.as_pcwstr()
+    }
+}
@@ -1,8 +1,8 @@
 use std::mem;
 
-use me3_binary_analysis::rtti::ClassMap;
+use me3_binary_analysis::{rtti::ClassMap, signature::Signature};
 use pelite::pe::Pe;
-use regex::bytes::Regex;
+use tracing::error;
 use windows::core::PCWSTR;
 
 use crate::mapping::{VfsOverride, VfsOverrideMapping};
@@ -74,7 +74,11 @@ pub fn find_override<'a>(mapping: &'a VfsOverrideMapping, input: &str) -> Option
 fn get_override<'a>(mapping: &'a VfsOverrideMapping, input: &str) -> Option<&'a VfsOverride> {
     for prefix in PREFIXES {
         let prefixed = format!("{prefix}/{input}");
-        if let Some(replacement) = mapping.vfs_override(&prefixed) {
+        // Hiding sound archive entries is not supported, keep using the original.
+        if let Some(replacement) = mapping
+            .vfs_override(&prefixed)
+            .filter(|replacement| !replacement.is_hidden())
+        {
             return Some(replacement);
         }
     }
@@ -147,26 +151,18 @@ where
     // je     ??
     // add    reg,0x38
     // cmp    QWORD PTR [rbp+0x0],0x8
-    let open_file_re = Regex::new(
-        r"(?s-u)\xe8(.{4})\x83\xf8\x01(?:(?:\x74.)|(?:\x0f\x84.{4}))[\x48-\x4f]\x83[\xc0-\xc7]\x38[\x48-\x4f]\x83(?:(?:\x7d.)|(?:\xbd.{4}))\x08",
+    let open_file = Signature::new(
+        "E8 [rel32] 83 F8 01 (74 ?? | 0F 84 ?? ?? ?? ??) 48-4F 83 C0-C7 38 48-4F 83 (7D ?? | BD ?? ?? ?? ??) 08",
     )
     .unwrap();
 
-    let call_disp32 = open_file_re
-        .captures(text)
-        .and_then(|c| c.iter().nth(1).flatten())?
-        .as_bytes();
+    let open_by_name = open_file
+        .find_mapped(text)
+        .inspect_err(|e| error!(error = %e, "failed to find WwiseOpenFileByName"))
+        .ok()?
+        .nth(0)?;
 
-    let call_bytes = <[u8; 4]>::try_from(call_disp32).unwrap();
-
-    let open_by_name = unsafe {
-        mem::transmute(
-            call_disp32
-                .as_ptr_range()
-                .end
-                .offset(i32::from_le_bytes(call_bytes) as _),
-        )
-    };
+    let open_by_name = unsafe { mem::transmute(open_by_name as *const ()) };
 
     Some(open_by_name)
 }
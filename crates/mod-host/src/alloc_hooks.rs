@@ -1,12 +1,11 @@
-use std::{collections::BTreeMap, mem, ptr::NonNull, slice, sync::OnceLock};
+use std::{mem, ptr::NonNull, slice, sync::OnceLock};
 
 use eyre::{eyre, OptionExt};
-use me3_binary_analysis::{pe, rtti::ClassMap};
+use me3_binary_analysis::{patterns, pe, rtti::ClassMap};
 use me3_launcher_attach_protocol::AttachConfig;
 use me3_mod_host_types::alloc::DlAllocator;
 use me3_mod_protocol::Game;
 use pelite::pe::{Pe, PeObject};
-use regex::bytes::Regex;
 use tracing::{debug, info, instrument};
 
 use crate::{executable::Executable, host::ModHost};
@@ -30,27 +29,14 @@ pub fn hook_system_allocator(
     exe: Executable,
 ) -> Result<(), eyre::Error> {
     fn hook_system_allocator_inner(exe: Executable) -> Result<(), eyre::Error> {
-        let re = Regex::new(
-            r"(?s-u)\xe8.{4}\x48\x8b\x74\x24\x30\x48\x8b\x5c\x24\x38\x48\x83\xc4\x20\x5f\xe9(.{4})",
-        )
-        .unwrap();
-
         let text_section =
             pe::section(exe, ".text").map_err(|_| eyre!(".text section is missing"))?;
         let text = exe.get_section_bytes(text_section)?;
 
-        let Some((_, [disp32 @ &[b0, b1, b2, b3]])) = re.captures(text).map(|c| c.extract()) else {
-            return Err(eyre!("system allocator pattern returned no matches"));
-        };
+        let ptr = patterns::find_target(patterns::SYSTEM_ALLOCATOR, text, text.as_ptr() as u64)?;
 
         let get_system_allocator = unsafe {
-            let ptr = disp32
-                .as_ptr_range()
-                .end
-                .byte_offset(i32::from_le_bytes([b0, b1, b2, b3]) as _)
-                as *const ();
-
-            mem::transmute::<_, unsafe extern "C" fn() -> NonNull<DlAllocator>>(ptr)
+            mem::transmute::<_, unsafe extern "C" fn() -> NonNull<DlAllocator>>(ptr as *const ())
         };
 
         ModHost::get_attached()
@@ -142,77 +128,31 @@ pub fn hook_heap_allocators(
 }
 
 fn find_cs_memory_vtable(exe: Executable) -> Result<&'static CSMemoryVtable, eyre::Error> {
-    let vtable_re = Regex::new(
-        r"(?s-u)\xe8.{4}\x90\x48\x8d\x05(.{4})\x48\x89\x03\xc6\x83.\x02\x00\x00\x00\x48\x8b\xc3\x48\x83\xc4.\x5b\xc3"
-    )
-    .unwrap();
-
-    let Some((_, [disp32 @ &[b0, b1, b2, b3]])) =
-        vtable_re.captures(exe.image()).map(|c| c.extract())
-    else {
-        return Err(eyre!("CSMemoryImp pattern returned no matches"));
-    };
+    let image = exe.image();
+    let vtable_ptr =
+        patterns::find_target(patterns::CS_MEMORY_VTABLE, image, image.as_ptr() as u64)?;
 
-    unsafe {
-        let vtable_ptr = disp32
-            .as_ptr_range()
-            .end
-            .byte_offset(i32::from_le_bytes([b0, b1, b2, b3]) as _)
-            as *const CSMemoryVtable;
-
-        Ok(&*vtable_ptr)
-    }
+    unsafe { Ok(&*(vtable_ptr as *const CSMemoryVtable)) }
 }
 
 fn allocator_table_mut(
     attach_config: &AttachConfig,
     exe: Executable,
 ) -> Result<&'static mut [Option<NonNull<DlAllocator>>], eyre::Error> {
-    let first_re =
-        Regex::new(r"(?s-u)\x48\x89\x05(.{4})\x4c\x8b\xc0\xba\x08\x00\x00\x00\x8d\x4a\x08")
-            .unwrap();
-
-    let Some((_, [disp32 @ &[b0, b1, b2, b3]])) =
-        first_re.captures(exe.image()).map(|c| c.extract())
-    else {
-        return Err(eyre!("first allocator pattern returned no matches"));
-    };
+    let image = exe.image();
 
-    let first_ptr = unsafe {
-        disp32
-            .as_ptr_range()
-            .end
-            .byte_offset(i32::from_le_bytes([b0, b1, b2, b3]) as _)
-            as *mut Option<NonNull<DlAllocator>>
-    };
+    let first_ptr = patterns::find_target(patterns::FIRST_ALLOCATOR, image, image.as_ptr() as u64)?
+        as *mut Option<NonNull<DlAllocator>>;
 
-    let re_str = match attach_config.game {
-        Game::DarkSouls3 => r"(?s-u)\x48\x89\x05(.{4})\x4c\x8b\xc0\xba\x08\x00\x00\x00\x8d\x4a\x78",
-        Game::Sekiro => r"(?s-u)\x48\x89\x05(.{4})\x4c\x8b\xc0\xba\x08\x00\x00\x00\x8d\x4a\x70",
-        _ => {
-            r"(?s-u)\x48\x89\x3d(.{4})\xc7\x44\x24\x20\xff\xff\xff\xff\x45\x33\xc9\x4c\x8b\xc7\x48\x8d\x15.{4}"
-        }
+    let signature_str = match attach_config.game {
+        Game::DarkSouls3 => patterns::LAST_ALLOCATOR_DS3,
+        Game::Sekiro => patterns::LAST_ALLOCATOR_SDT,
+        _ => patterns::LAST_ALLOCATOR_ER,
     };
 
-    let last_re = Regex::new(re_str).unwrap();
-
-    let mut last_candidates = last_re
-        .captures_iter(exe.image())
-        .map(|c| c.extract())
-        .map(|(_, [disp32])| unsafe {
-            disp32
-                .as_ptr_range()
-                .end
-                .byte_offset(i32::from_le_bytes(disp32.try_into().unwrap()) as _)
-                as *mut Option<NonNull<DlAllocator>>
-        })
-        .collect::<Vec<_>>();
-
-    last_candidates.sort();
-
-    let last_ptr = *last_candidates
-        .last()
-        .ok_or_eyre("last allocator pattern returned no matches")?;
+    let last_ptr = patterns::highest_target(signature_str, image, image.as_ptr() as u64)
+        .ok_or_eyre("last allocator pattern returned no matches")?
+        as *mut Option<NonNull<DlAllocator>>;
 
     if first_ptr > last_ptr {
         return Err(eyre!("malformed allocator range"));
@@ -227,27 +167,15 @@ fn allocator_table_mut(
 }
 
 fn patch_ds3(exe: Executable) -> Result<(), eyre::Error> {
-    let re = Regex::new(
-        r"(?s-u)\x48\x8b\x1d.{4}\x48\x8b\x0d.{4}\xe8(.{4})\x48\x8b\xd0\x45\x33\xc0\x48\x8b\xcb\xe8.{4}\xe8.{4}",
-    )
-    .unwrap();
-
     let text_section = pe::section(exe, ".text").map_err(|_| eyre!(".text section is missing"))?;
     let text = exe.get_section_bytes(text_section)?;
 
-    let Some((_, [disp32 @ &[b0, b1, b2, b3]])) = re.captures(text).map(|c| c.extract()) else {
-        return Err(eyre!("debug allocator getter pattern returned no matches"));
-    };
-
-    let ptr = unsafe {
-        disp32
-            .as_ptr_range()
-            .end
-            .byte_offset(i32::from_le_bytes([b0, b1, b2, b3]) as _) as *const ()
-    };
+    let ptr = patterns::find_target(patterns::DEBUG_ALLOCATOR_DS3, text, text.as_ptr() as u64)?;
 
     let fn_ptr = unsafe {
-        mem::transmute::<_, unsafe extern "C" fn(*const ()) -> NonNull<DlAllocator>>(ptr)
+        mem::transmute::<_, unsafe extern "C" fn(*const ()) -> NonNull<DlAllocator>>(
+            ptr as *const (),
+        )
     };
 
     ModHost::get_attached()
@@ -264,34 +192,17 @@ fn patch_ds3(exe: Executable) -> Result<(), eyre::Error> {
 }
 
 fn patch_sdt(exe: Executable) -> Result<(), eyre::Error> {
-    let re = Regex::new(
-        r"(?s-u)\xe8(.{4})\x48\x89\x44\x24.\x4c\x8b\xc0\xba\x08\x00\x00\x00\xb9\x90\x00\x00\x00\xe8.{4}",
-    )
-    .unwrap();
-
     let text_section = pe::section(exe, ".text").map_err(|_| eyre!(".text section is missing"))?;
     let text = exe.get_section_bytes(text_section)?;
 
-    let (ptr, _) = re
-        .captures_iter(text)
-        .map(|c| c.extract())
-        .map(|(_, [disp32])| unsafe {
-            disp32
-                .as_ptr_range()
-                .end
-                .byte_offset(i32::from_le_bytes(disp32.try_into().unwrap()) as _)
-                as *const ()
-        })
-        .fold(BTreeMap::<_, usize>::new(), |mut all, ptr| {
-            *all.entry(ptr).or_default() += 1;
-            all
-        })
-        .into_iter()
-        .max_by_key(|(_, i)| *i)
-        .ok_or_eyre("debug allocator getter pattern returned no matches")?;
+    let ptr =
+        patterns::most_common_target(patterns::DEBUG_ALLOCATOR_SDT, text, text.as_ptr() as u64)
+            .ok_or_eyre("debug allocator getter pattern returned no matches")?;
 
     let fn_ptr = unsafe {
-        mem::transmute::<_, unsafe extern "C" fn(*const ()) -> NonNull<DlAllocator>>(ptr)
+        mem::transmute::<_, unsafe extern "C" fn(*const ()) -> NonNull<DlAllocator>>(
+            ptr as *const (),
+        )
     };
 
     ModHost::get_attached()
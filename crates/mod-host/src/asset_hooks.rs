@@ -73,7 +73,7 @@ fn enable_loose_params(attach_config: &AttachConfig, mapping: &VfsOverrideMappin
 
     if LOOSE_PARAM_FILES
         .iter()
-        .any(|file| mapping.vfs_override(file).is_some())
+        .any(|file| mapping.vfs_override(file).is_some_and(|o| !o.is_hidden()))
     {
         ModHost::get_attached().override_game_property("Game.Debug.EnableRegulationFile", false);
     }
@@ -162,6 +162,11 @@ fn hook_device_manager(
 ) -> Result<(), eyre::Error> {
     let device_manager = locate_device_manager(exe)?;
 
+    enum OpenOverride {
+        Replace(DlUtf16String),
+        Hide,
+    }
+
     let open_disk_file = DlDeviceManager::lock(device_manager).open_disk_file();
 
     let override_path = {
@@ -173,13 +178,18 @@ fn hook_device_manager(
 
             let mapped_override = mapping.vfs_override(OsString::from_wide(&expanded))?;
 
-            info!("override" = %mapped_override);
+            if mapped_override.is_hidden() {
+                info!("hidden" = %mapped_override, package = mapped_override.package());
+                return Some(OpenOverride::Hide);
+            }
+
+            info!("override" = %mapped_override, package = mapped_override.package());
 
             let mut path = path.clone();
 
             path.replace_from_slice(mapped_override.as_ref());
 
-            Some(path)
+            Some(OpenOverride::Replace(path))
         }
     };
 
@@ -193,8 +203,10 @@ fn hook_device_manager(
         .hook(open_disk_file)
         .with_span(info_span!("hook"))
         .with_closure(move |p1, path, p3, p4, p5, p6, trampoline| {
-            let file_operator = if let Some(path) = override_path(unsafe { path.as_ref() }) {
-                unsafe {
+            let file_operator = match override_path(unsafe { path.as_ref() }) {
+                // Report hidden files as absent instead of falling back to the game archives.
+                Some(OpenOverride::Hide) => return None,
+                Some(OpenOverride::Replace(path)) => unsafe {
                     trampoline(
                         p1,
                         NonNull::from(&path).cast(),
@@ -203,9 +215,8 @@ fn hook_device_manager(
                         p5,
                         p6,
                     )
-                }
-            } else {
-                unsafe { trampoline(p1, path, p3, p4, p5, p6) }
+                },
+                None => unsafe { trampoline(p1, path, p3, p4, p5, p6) },
             };
 
             if let Some(file_operator) = file_operator {
@@ -244,7 +255,9 @@ fn hook_set_path(
 
         let expanded = DlDeviceManager::lock(device_manager).expand_path(path.as_slice());
 
-        let mapped_override = mapping.vfs_override(OsString::from_wide(&expanded))?;
+        let mapped_override = mapping
+            .vfs_override(OsString::from_wide(&expanded))
+            .filter(|o| !o.is_hidden())?;
 
         let mut path = path.clone();
 
@@ -513,7 +526,7 @@ fn try_hook_wwise(
             let path_string = unsafe { path.to_string().unwrap() };
 
             if let Some(mapped_override) = wwise::find_override(&mapping, &path_string) {
-                info!("override" = %mapped_override);
+                info!("override" = %mapped_override, package = mapped_override.package());
 
                 // Force lookup to wwise's ordinary read (from disk) mode instead of the EBL read.
                 unsafe {
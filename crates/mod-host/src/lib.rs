@@ -14,6 +14,7 @@ use me3_binary_analysis::{fd4_step::Fd4StepTables, rtti};
 use me3_env::TelemetryVars;
 use me3_launcher_attach_protocol::{AttachConfig, AttachRequest, AttachResult, Attachment};
 use me3_mod_host_assets::mapping::VfsOverrideMapping;
+use me3_mod_protocol::Game;
 use me3_telemetry::TelemetryConfig;
 use tracing::{error, info, warn, Span};
 use windows::Win32::{
@@ -90,7 +91,20 @@ fn on_attach(request: AttachRequest) -> AttachResult {
     let _ = unsafe { TELEMETRY_INSTANCE.set(telemetry_guard) };
 
     let result = me3_telemetry::with_root_span("host", "attach", move || {
-        info!("Beginning host attach");
+        info!(
+            "game" = %attach_config.game,
+            "game_fingerprint" = attach_config.game_fingerprint.as_deref(),
+            "game_build" = attach_config.game_build.as_deref(),
+            "Beginning host attach"
+        );
+
+        if let Some(fingerprint) = &attach_config.game_fingerprint {
+            me3_telemetry::set_tag("game.fingerprint", fingerprint);
+        }
+
+        if let Some(build) = &attach_config.game_build {
+            me3_telemetry::set_tag("game.build", build);
+        }
 
         // SAFETY: process is still suspended.
         let exe = unsafe { Executable::new() };
@@ -109,6 +123,12 @@ fn on_attach(request: AttachRequest) -> AttachResult {
 
         let mut override_mapping = VfsOverrideMapping::new()?;
 
+        if let Some(cache_path) = &attach_config.cache_path {
+            override_mapping.set_cache_dir(cache_path);
+        }
+
+        override_mapping.set_force_rescan(attach_config.rescan_packages);
+
         override_mapping.scan_directories(attach_config.packages.iter())?;
         savefile::attach_override(&attach_config, &mut override_mapping)?;
 
@@ -164,6 +184,19 @@ fn after_game_main<R: FnOnce() -> Result<(), eyre::Error>>(
 ) -> Result<(), eyre::Error> {
     before_main_result()?;
 
+    // Games before Dark Souls 3 don't have the task steps and file devices the hooks below rely on,
+    // so only natives are loaded into them.
+    if attach_config.game < Game::DarkSouls3 {
+        if !attach_config.packages.is_empty() {
+            warn!(
+                "game" = %attach_config.game,
+                "archive overrides are not supported; packages only override files read from disk"
+            );
+        }
+
+        return load_natives(&attach_config);
+    }
+
     let class_map = Arc::new(rtti::classes(exe)?);
     let step_tables = Fd4StepTables::from_initialized_data(exe)?;
 
@@ -178,6 +211,23 @@ fn after_game_main<R: FnOnce() -> Result<(), eyre::Error>>(
         override_mapping.clone(),
     )?;
 
+    load_natives(&attach_config)?;
+
+    asset_hooks::attach_override(
+        attach_config,
+        exe,
+        class_map,
+        &step_tables,
+        override_mapping,
+    )
+    .map_err(|e| {
+        e.wrap_err("failed to attach asset override hooks; no files will be overridden")
+    })?;
+
+    Ok(())
+}
+
+fn load_natives(attach_config: &AttachConfig) -> Result<(), eyre::Error> {
     let first_delayed_offset = attach_config
         .natives
         .iter()
@@ -219,17 +269,6 @@ fn after_game_main<R: FnOnce() -> Result<(), eyre::Error>>(
         }
     });
 
-    asset_hooks::attach_override(
-        attach_config,
-        exe,
-        class_map,
-        &step_tables,
-        override_mapping,
-    )
-    .map_err(|e| {
-        e.wrap_err("failed to attach asset override hooks; no files will be overridden")
-    })?;
-
     Ok(())
 }
 
@@ -1,6 +1,8 @@
 use std::{
     alloc::{GlobalAlloc, Layout},
+    ffi::OsString,
     fs, mem,
+    os::windows::ffi::OsStringExt,
     path::{Path, PathBuf},
     ptr::NonNull,
     sync::Arc,
@@ -8,14 +10,20 @@ use std::{
 
 use eyre::{eyre, OptionExt};
 use from_singleton::FromSingleton;
-use me3_binary_analysis::{fd4_step::Fd4StepTables, pe};
+use me3_binary_analysis::{fd4_step::Fd4StepTables, pe, signature::Signature};
 use me3_launcher_attach_protocol::AttachConfig;
 use me3_mod_host_assets::mapping::VfsOverrideMapping;
 use me3_mod_host_types::{alloc::DlStdAllocator, vector::DlVector};
-use me3_mod_protocol::Game;
+use me3_mod_protocol::{
+    variables::{PathVariables, DOCUMENTS_DIR},
+    Game,
+};
 use pelite::pe::{Pe, Va};
-use regex::bytes::Regex;
 use tracing::{error, info, instrument, warn, Span};
+use windows::Win32::{
+    System::Com::CoTaskMemFree,
+    UI::Shell::{FOLDERID_Documents, SHGetKnownFolderPath, KF_FLAG_DEFAULT},
+};
 
 use crate::{executable::Executable, host::ModHost};
 
@@ -28,10 +36,12 @@ pub fn attach_override(
 ) -> Result<(), eyre::Error> {
     if let Some(override_name) = &attach_config.savefile {
         let savefile_dir = attach_config
-            .game
-            .savefile_dir()
+            .savefile_dir
+            .as_deref()
             .ok_or_eyre("unable to locate savefile directory")?;
 
+        let savefile_dir = savefile_dir_variables().expand(savefile_dir)?;
+
         let span = Span::current();
         let override_name = override_name.clone();
 
@@ -51,6 +61,31 @@ pub fn attach_override(
     Ok(())
 }
 
+/// The variables a savefile directory can refer to besides environment variables.
+fn savefile_dir_variables() -> PathVariables {
+    let mut variables = PathVariables::new();
+
+    match documents_dir() {
+        Ok(documents_dir) => {
+            variables.set(DOCUMENTS_DIR, documents_dir);
+        }
+        Err(e) => warn!("error" = %e, "unable to locate the Documents folder"),
+    }
+
+    variables
+}
+
+fn documents_dir() -> windows::core::Result<PathBuf> {
+    unsafe {
+        let path = SHGetKnownFolderPath(&FOLDERID_Documents, KF_FLAG_DEFAULT, None)?;
+        let documents_dir = PathBuf::from(OsString::from_wide(path.as_wide()));
+
+        CoTaskMemFree(Some(path.as_ptr() as *const _));
+
+        Ok(documents_dir)
+    }
+}
+
 fn override_savefile_path(
     current_path: &Path,
     override_name: &str,
@@ -142,7 +177,7 @@ fn oversized_regulation_fix_for_sdt(exe: Executable) -> Result<(), eyre::Error>
     // call   ??
     // test   al,al
     // je     ??
-    let call_re = Regex::new(r"(s?-u)\x48\x8d\x4c\x24.\xe8(.{4})\x84\xc0\x74.").unwrap();
+    let call = Signature::new("48 8D 4C 24 ?? E8 [rel32] 84 C0 74 ??").unwrap();
 
     // matches:
     // mov    rdx,rcx
@@ -151,25 +186,19 @@ fn oversized_regulation_fix_for_sdt(exe: Executable) -> Result<(), eyre::Error>
     // jne    ??
     // xor    al,al
     // ret
-    let fn_re = Regex::new(
-        r"(?s-u)\A\x48\x8b\xd1\x48\x8b\x0d.{4}\x48\x85\xc9(?:(?:\x75.)|(?:\x0f\x85.{4}))\x32\xc0\xc3",
+    let function = Signature::new(
+        "48 8B D1 48 8B 0D ?? ?? ?? ?? 48 85 C9 (75 ?? | 0F 85 ?? ?? ?? ??) 32 C0 C3",
     )
     .unwrap();
 
     // Intercept and skip writing the regulation to the savefile.
-    call_re
-        .captures_iter(text)
-        .filter_map(|c| {
-            let [disp32] = c.extract().1;
-
-            let fn_ptr = disp32
-                .as_ptr_range()
-                .end
-                .wrapping_offset(i32::from_le_bytes(disp32.try_into().unwrap()) as isize);
+    call.scan_mapped(text)
+        .filter_map(|m| {
+            let fn_ptr = m.nth(0)? as *const u8;
 
             if text.as_ptr_range().contains(&fn_ptr)
                 && let Ok(fn_start) = exe.read_bytes(fn_ptr as Va)
-                && fn_re.is_match_at(fn_start, 0)
+                && function.match_at(fn_start, fn_ptr as Va).is_some()
             {
                 unsafe {
                     Some(mem::transmute::<_, unsafe extern "C" fn(usize) -> bool>(
@@ -1,13 +1,13 @@
 use std::{mem, ptr, sync::Arc};
 
 use eyre::{eyre, OptionExt};
-use me3_binary_analysis::pe;
+use me3_binary_analysis::{pe, signature::Signature};
 use me3_launcher_attach_protocol::AttachConfig;
 use me3_mod_protocol::Game;
 use pelite::pe::Pe;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use regex::bytes::Regex;
-use tracing::{error, info, instrument, Span};
+use tracing::{debug, error, info, instrument, Span};
 use windows::{
     core::{s, w},
     Win32::{
@@ -31,6 +31,11 @@ pub fn attach_override(
 ) -> Result<(), eyre::Error> {
     fix_show_window_flash()?;
 
+    if attach_config.game < Game::DarkSouls3 {
+        debug!("game" = %attach_config.game, "skipping unsupported game");
+        return Ok(());
+    }
+
     defer_init(Span::current(), Deferred::AfterMain, move || {
         if attach_config.skip_logos {
             // Different hooks depending on engine version.
@@ -113,15 +118,16 @@ fn skip_sprj_logos(exe: Executable) -> Result<(), eyre::Error> {
     // mov    QWORD PTR [rsp+??],rbx
     // mov    rdi,rcx
     // mov    BYTE PTR [rip+??],0x1
-    let step_re = Regex::new(
-        r"(?s-u)\x40\x55\x56\x57\x48\x8d\x6c\x24.\x48\x81\xec.{4}\x48\xc7\x45.\xfe\xff\xff\xff\x48\x89\x9c\x24.{4}\x48\x8b\xf9\xc6\x05.{4}\x01",
+    let step = Signature::new(
+        "40 55 56 57 48 8D 6C 24 ?? 48 81 EC ?? ?? ?? ?? 48 C7 45 ?? FE FF FF FF 48 89 9C 24 ?? ?? ?? ?? 48 8B F9 C6 05 ?? ?? ?? ?? 01",
     )
     .unwrap();
 
     // Find the function in the .text section.
-    let step_ptr = step_re
-        .find(text)
-        .map(|m| m.as_bytes().as_ptr() as usize)
+    let step_ptr = step
+        .scan_mapped(text)
+        .next()
+        .map(|m| m.va() as usize)
         .ok_or_eyre("pattern returned no matches")?;
 
     let (_, data_ptrs, _) = unsafe { data.align_to::<usize>() };
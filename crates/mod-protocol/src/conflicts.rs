@@ -0,0 +1,141 @@
+//! Detection of asset paths that are provided by more than one package.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+use crate::{dependency::Dependency, package::Package};
+
+/// An asset path that is provided by more than one package.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetConflict {
+    /// The asset path relative to the package roots, lowercased like asset lookups are.
+    pub path: PathBuf,
+
+    /// IDs of all packages providing this asset, in load order.
+    pub providers: Vec<String>,
+
+    /// Whether the winning package is explicitly ordered after every other provider through
+    /// `load_before` or `load_after`, rather than winning because of its position in the profile.
+    pub resolved: bool,
+}
+
+impl AssetConflict {
+    /// ID of the package whose asset will be used. Packages that are loaded later take precedence.
+    pub fn winner(&self) -> &str {
+        self.providers.last().expect("conflict without providers")
+    }
+}
+
+/// Explicit `load_before` and `load_after` relations between packages.
+pub struct PackageOrdering {
+    successors: HashMap<String, Vec<String>>,
+}
+
+impl PackageOrdering {
+    pub fn new(packages: &[Package]) -> Self {
+        let mut successors = HashMap::<String, Vec<String>>::new();
+
+        for package in packages {
+            let id = package.id();
+
+            for before in package.loads_before() {
+                successors.entry(id.clone()).or_default().push(before.id());
+            }
+
+            for after in package.loads_after() {
+                successors.entry(after.id()).or_default().push(id.clone());
+            }
+        }
+
+        Self { successors }
+    }
+
+    /// Describe an asset that is provided by more than one package.
+    ///
+    /// `providers` are the IDs of the packages providing the asset in load order.
+    pub fn conflict(&self, path: PathBuf, providers: Vec<String>) -> AssetConflict {
+        let (winner, others) = providers.split_last().expect("conflict without providers");
+        let resolved = others
+            .iter()
+            .all(|other| self.is_ordered_before(other, winner));
+
+        AssetConflict {
+            path,
+            providers,
+            resolved,
+        }
+    }
+
+    /// Whether `first` is explicitly, directly or transitively, ordered before `second`.
+    fn is_ordered_before(&self, first: &str, second: &str) -> bool {
+        let mut visited = HashSet::new();
+        let mut queue = vec![first];
+
+        while let Some(id) = queue.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+
+            for successor in self.successors.get(id).into_iter().flatten() {
+                if successor == second {
+                    return true;
+                }
+
+                queue.push(successor);
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::PackageOrdering;
+    use crate::ModProfile;
+
+    #[test]
+    fn conflicts_are_resolved_by_explicit_ordering() {
+        let profile: ModProfile = toml::from_str(
+            r#"
+profileVersion = "v1"
+
+[[packages]]
+id = "a"
+path = "a"
+
+[[packages]]
+id = "b"
+path = "b"
+load_after = [{ id = "a", optional = false }]
+
+[[packages]]
+id = "c"
+path = "c"
+load_after = [{ id = "b", optional = false }]
+
+[[packages]]
+id = "d"
+path = "d"
+"#,
+        )
+        .unwrap();
+
+        let ordering = PackageOrdering::new(&profile.packages());
+        let conflict = |providers: &[&str]| {
+            ordering.conflict(
+                PathBuf::from("regulation.bin"),
+                providers.iter().map(|id| id.to_string()).collect(),
+            )
+        };
+
+        assert!(conflict(&["a", "b"]).resolved);
+        assert!(conflict(&["a", "b", "c"]).resolved);
+        assert!(!conflict(&["a", "d"]).resolved);
+        assert_eq!(conflict(&["a", "d"]).winner(), "d");
+    }
+}
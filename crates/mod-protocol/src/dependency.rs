@@ -1,10 +1,12 @@
 use std::{
-    collections::{BinaryHeap, HashSet},
+    collections::{BinaryHeap, HashMap},
+    fmt,
     hash::{Hash, RandomState},
 };
 
 use indexmap::{map::Entry, IndexMap};
 use schemars::JsonSchema;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 
 pub trait DependencyId: Eq + PartialEq + Hash + Clone {}
@@ -14,14 +16,61 @@ impl<T: Eq + PartialEq + Hash + Clone + for<'de> Deserialize<'de> + Serialize> D
 pub struct Dependent<T: DependencyId> {
     id: T,
     optional: bool,
+
+    /// The versions of the dependency that are supported, e.g. `>=1.2` or `~2.0.3`.
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    version: Option<VersionReq>,
 }
 
 impl<T: DependencyId> Dependent<T> {
     pub fn id(&self) -> T {
         self.id.clone()
     }
+
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+
+    /// The same reference, to the entry with ID `id` instead.
+    pub(crate) fn to(&self, id: T) -> Self {
+        Self { id, ..self.clone() }
+    }
+
+    pub fn version(&self) -> Option<&VersionReq> {
+        self.version.as_ref()
+    }
+}
+
+/// A reference to another entry that must, or must not, be loaded together with the entry that
+/// declares it.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Relation<T: DependencyId> {
+    id: T,
+
+    /// The versions of the other entry this applies to, e.g. `>=1.2`. Applies to every version if
+    /// not set.
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    version: Option<VersionReq>,
 }
 
+impl<T: DependencyId> Relation<T> {
+    pub fn id(&self) -> T {
+        self.id.clone()
+    }
+
+    /// The same reference, to the entry with ID `id` instead.
+    pub(crate) fn to(&self, id: T) -> Self {
+        Self { id, ..self.clone() }
+    }
+
+    pub fn version(&self) -> Option<&VersionReq> {
+        self.version.as_ref()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DependencyOrder {
     Before,
     After,
@@ -31,6 +80,7 @@ pub struct DependencyLink<T: DependencyId> {
     optional: bool,
     order: DependencyOrder,
     id: T,
+    version: Option<VersionReq>,
 }
 
 pub trait Dependency {
@@ -38,6 +88,11 @@ pub trait Dependency {
 
     fn id(&self) -> Self::UniqueId;
 
+    /// The version of this item that dependents are checked against.
+    fn version(&self) -> Option<&Version> {
+        None
+    }
+
     fn dependencies(&self) -> impl Iterator<Item = DependencyLink<Self::UniqueId>> {
         self.loads_after()
             .iter()
@@ -45,17 +100,35 @@ pub trait Dependency {
                 optional: dep.optional,
                 order: DependencyOrder::After,
                 id: dep.id(),
+                version: dep.version.clone(),
             })
             .chain(self.loads_before().iter().map(|dep| DependencyLink {
                 optional: dep.optional,
                 order: DependencyOrder::Before,
                 id: dep.id(),
+                version: dep.version.clone(),
             }))
     }
 
     fn loads_after(&self) -> &[Dependent<Self::UniqueId>];
 
     fn loads_before(&self) -> &[Dependent<Self::UniqueId>];
+
+    /// Entries that must be loaded for this item to work, in any order.
+    fn requires(&self) -> &[Relation<Self::UniqueId>] {
+        &[]
+    }
+
+    /// Entries that can't be loaded together with this item.
+    fn conflicts_with(&self) -> &[Relation<Self::UniqueId>] {
+        &[]
+    }
+
+    /// Whether this item is loaded. The requirements and conflicts of disabled items are ignored,
+    /// and disabled items don't satisfy or violate those of other items.
+    fn is_enabled(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -63,21 +136,99 @@ pub enum DependencyError<T: Dependency> {
     #[error("Required dependency is unavailable: {0}")]
     MissingDependency(T::UniqueId),
 
-    #[error("Dependencies resulted in cycles, remaining dependencies: {0:?}")]
-    Cyclic(Vec<T::UniqueId>),
+    #[error("{0} is used as the ID of more than one entry")]
+    DuplicateId(T::UniqueId),
+
+    #[error("{dependent} requires {id} {required}, but version {found} is loaded")]
+    IncompatibleVersion {
+        dependent: T::UniqueId,
+        id: T::UniqueId,
+        required: VersionReq,
+        found: Version,
+    },
+
+    #[error("{dependent} requires {id} {required}, but {id} does not declare a version")]
+    UnknownVersion {
+        dependent: T::UniqueId,
+        id: T::UniqueId,
+        required: VersionReq,
+    },
+
+    #[error("{dependent} requires {id}, which is not loaded")]
+    MissingRequirement {
+        dependent: T::UniqueId,
+        id: T::UniqueId,
+    },
+
+    #[error("{dependent} is incompatible with {id}, which is loaded")]
+    Conflict {
+        dependent: T::UniqueId,
+        id: T::UniqueId,
+    },
+
+    #[error("Dependencies form a cycle: {0}")]
+    Cyclic(DependencyCycle<T::UniqueId>),
+}
+
+/// An ordering relation that is part of a cycle, `from` has to load before `to`.
+#[derive(Clone, Debug)]
+pub struct CycleEdge<T> {
+    pub from: T,
+    pub to: T,
+    /// [`DependencyOrder::Before`] if the relation is declared by `load_before` in `from`,
+    /// [`DependencyOrder::After`] if it is declared by `load_after` in `to`.
+    pub order: DependencyOrder,
+}
+
+/// A sequence of ordering relations that leads back to where it started, so the items in it can't
+/// be ordered.
+#[derive(Clone, Debug)]
+pub struct DependencyCycle<T>(Vec<CycleEdge<T>>);
+
+impl<T> DependencyCycle<T> {
+    pub fn edges(&self) -> &[CycleEdge<T>] {
+        &self.0
+    }
+
+    /// IDs of the items in the cycle.
+    pub fn ids(&self) -> impl Iterator<Item = &T> {
+        self.0.iter().map(|edge| &edge.from)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for DependencyCycle<T> {
+    /// Formats the cycle like `a -> b (load_before of a) -> a (load_after of a)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(first) = self.0.first() else {
+            return Ok(());
+        };
+
+        write!(f, "{}", first.from)?;
+
+        for edge in &self.0 {
+            match edge.order {
+                DependencyOrder::Before => {
+                    write!(f, " -> {} (load_before of {})", edge.to, edge.from)?
+                }
+                DependencyOrder::After => write!(f, " -> {} (load_after of {})", edge.to, edge.to)?,
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
 struct DependencyNode<T> {
     num_prec: usize,
-    succ: HashSet<T>,
+    succ: IndexMap<T, DependencyOrder>,
 }
 
 impl<T> DependencyNode<T> {
     fn new() -> Self {
         Self {
             num_prec: 0,
-            succ: HashSet::new(),
+            succ: IndexMap::new(),
         }
     }
 }
@@ -85,7 +236,12 @@ impl<T> DependencyNode<T> {
 trait DependencySorter {
     type UniqueId: DependencyId;
 
-    fn add_dependency(&mut self, prec: Self::UniqueId, succ: Self::UniqueId);
+    fn add_dependency(
+        &mut self,
+        prec: Self::UniqueId,
+        succ: Self::UniqueId,
+        order: DependencyOrder,
+    );
     fn pop_dependency(&mut self) -> Option<(Self::UniqueId, bool)>;
 }
 
@@ -96,19 +252,20 @@ where
 {
     type UniqueId = T;
 
-    fn add_dependency(&mut self, prec: T, succ: T) {
+    fn add_dependency(&mut self, prec: T, succ: T, order: DependencyOrder) {
         match self.entry(prec) {
             Entry::Vacant(e) => {
                 let mut dep = DependencyNode::new();
-                dep.succ.insert(succ.clone());
+                dep.succ.insert(succ.clone(), order);
                 e.insert(dep);
             }
-            Entry::Occupied(e) => {
-                if !e.into_mut().succ.insert(succ.clone()) {
-                    // Already registered
-                    return;
+            Entry::Occupied(e) => match e.into_mut().succ.entry(succ.clone()) {
+                // Already registered
+                Entry::Occupied(_) => return,
+                Entry::Vacant(e) => {
+                    e.insert(order);
                 }
-            }
+            },
         }
 
         match self.entry(succ) {
@@ -128,7 +285,7 @@ where
             .find_map(|(k, v)| (v.num_prec == 0).then_some(k.clone()))
             .map(|key| {
                 if let Some(p) = self.shift_remove(&key) {
-                    for s in &p.succ {
+                    for s in p.succ.keys() {
                         if let Some(y) = self.get_mut(s) {
                             y.num_prec -= 1;
                         }
@@ -176,21 +333,145 @@ impl<T> Ord for DependencyRun<T> {
     }
 }
 
+fn check_version<T: Dependency>(
+    dependent: &T::UniqueId,
+    id: &T::UniqueId,
+    required: &VersionReq,
+    dependency: &T,
+) -> Result<(), DependencyError<T>> {
+    match dependency.version() {
+        Some(found) if required.matches(found) => Ok(()),
+        Some(found) => Err(DependencyError::IncompatibleVersion {
+            dependent: dependent.clone(),
+            id: id.clone(),
+            required: required.clone(),
+            found: found.clone(),
+        }),
+        None => Err(DependencyError::UnknownVersion {
+            dependent: dependent.clone(),
+            id: id.clone(),
+            required: required.clone(),
+        }),
+    }
+}
+
+/// Checks that the requirements of every enabled item are loaded, and that none of the items it
+/// conflicts with are.
+fn check_relations<T: Dependency>(
+    all: &IndexMap<T::UniqueId, (T, usize), RandomState>,
+) -> Result<(), DependencyError<T>> {
+    let enabled = |id: &T::UniqueId| {
+        all.get(id)
+            .map(|(item, _)| item)
+            .filter(|item| item.is_enabled())
+    };
+
+    for (id, (item, _)) in all.iter().filter(|(_, (item, _))| item.is_enabled()) {
+        for requirement in item.requires() {
+            let Some(required) = enabled(&requirement.id) else {
+                return Err(DependencyError::MissingRequirement {
+                    dependent: id.clone(),
+                    id: requirement.id(),
+                });
+            };
+
+            if let Some(version) = &requirement.version {
+                check_version(id, &requirement.id, version, required)?;
+            }
+        }
+
+        for conflict in item.conflicts_with() {
+            let Some(conflicting) = enabled(&conflict.id) else {
+                continue;
+            };
+
+            // Entries without a version can't be known to be in a conflicting version range.
+            let in_range = match &conflict.version {
+                Some(version) => conflicting.version().is_some_and(|v| version.matches(v)),
+                None => true,
+            };
+
+            if in_range {
+                return Err(DependencyError::Conflict {
+                    dependent: id.clone(),
+                    id: conflict.id(),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds a cycle among the `remaining` nodes of a sort that couldn't be completed.
+///
+/// Every remaining node still has a remaining predecessor, so following predecessors from any of
+/// them eventually leads back to a node that was already visited.
+fn find_cycle<T: DependencyId>(remaining: &IndexMap<T, DependencyNode<T>>) -> DependencyCycle<T> {
+    let mut predecessors = HashMap::new();
+
+    for (id, node) in remaining {
+        for (succ, order) in &node.succ {
+            if remaining.contains_key(succ) {
+                predecessors.entry(succ).or_insert((id, *order));
+            }
+        }
+    }
+
+    let mut path = vec![];
+    let mut current = remaining.keys().next().expect("no remaining nodes");
+
+    let start = loop {
+        if let Some(start) = path.iter().position(|id| *id == current) {
+            break start;
+        }
+
+        path.push(current);
+        current = predecessors[current].0;
+    };
+
+    let mut cycle = path.split_off(start);
+    cycle.reverse();
+
+    let edges = cycle
+        .iter()
+        .zip(cycle.iter().cycle().skip(1))
+        .map(|(&from, &to)| CycleEdge {
+            from: from.clone(),
+            to: to.clone(),
+            order: predecessors[to].1,
+        })
+        .collect();
+
+    DependencyCycle(edges)
+}
+
 pub fn sort_dependencies<T: Dependency>(items: Vec<T>) -> Result<Vec<T>, DependencyError<T>> {
     let mut sorter = IndexMap::<T::UniqueId, DependencyNode<T::UniqueId>>::new();
-    let mut all = items
-        .into_iter()
-        .enumerate()
-        .map(|(index, item)| (item.id(), (item, index)))
-        .collect::<IndexMap<_, _, RandomState>>();
+    let mut all = IndexMap::<_, _, RandomState>::with_capacity(items.len());
+
+    for (index, item) in items.into_iter().enumerate() {
+        match all.entry(item.id()) {
+            Entry::Occupied(e) => return Err(DependencyError::DuplicateId(e.key().clone())),
+            Entry::Vacant(e) => {
+                e.insert((item, index));
+            }
+        }
+    }
+
+    check_relations(&all)?;
 
     for (id, (item, _)) in &all {
         for dep in item.dependencies() {
-            if !all.contains_key(&dep.id) {
+            let Some((dependency, _)) = all.get(&dep.id) else {
                 if !dep.optional {
                     return Err(DependencyError::MissingDependency(dep.id.clone()));
                 }
                 continue;
+            };
+
+            if let Some(required) = &dep.version {
+                check_version(id, &dep.id, required, dependency)?;
             }
 
             let (prec, succ) = match dep.order {
@@ -198,7 +479,7 @@ pub fn sort_dependencies<T: Dependency>(items: Vec<T>) -> Result<Vec<T>, Depende
                 DependencyOrder::After => (dep.id, id.clone()),
             };
 
-            sorter.add_dependency(prec, succ)
+            sorter.add_dependency(prec, succ, dep.order)
         }
     }
 
@@ -223,7 +504,7 @@ pub fn sort_dependencies<T: Dependency>(items: Vec<T>) -> Result<Vec<T>, Depende
     }
 
     if !sorter.is_empty() {
-        return Err(DependencyError::Cyclic(all.keys().cloned().collect()));
+        return Err(DependencyError::Cyclic(find_cycle(&sorter)));
     }
 
     let mut sorted = vec![];
@@ -244,9 +525,10 @@ pub fn sort_dependencies<T: Dependency>(items: Vec<T>) -> Result<Vec<T>, Depende
 mod tests {
     use std::path::PathBuf;
 
-    use super::{sort_dependencies, Dependent};
+    use super::{sort_dependencies, DependencyError, Dependent, Relation};
     use crate::{
         dependency::Dependency as _,
+        metadata::Metadata,
         package::{ModFile, Package},
     };
 
@@ -261,6 +543,15 @@ mod tests {
             path: ModFile(PathBuf::from(id)),
             load_after,
             load_before,
+            requires: vec![],
+            conflicts_with: vec![],
+            roots: vec![],
+            target: None,
+            include: vec![],
+            exclude: vec![],
+            mount_point: None,
+            hide: vec![],
+            metadata: Metadata::default(),
         }
     }
 
@@ -271,6 +562,7 @@ mod tests {
             vec![Dependent {
                 id: "pkg2".to_owned(),
                 optional: false,
+                version: None,
             }],
             vec![],
         );
@@ -280,11 +572,29 @@ mod tests {
             vec![Dependent {
                 id: "pkg1".to_owned(),
                 optional: false,
+                version: None,
             }],
             vec![],
         );
 
-        assert!(sort_dependencies(vec![pkg1, pkg2]).is_err())
+        let pkg3 = mock_package(
+            "pkg3",
+            vec![],
+            vec![Dependent {
+                id: "pkg1".to_owned(),
+                optional: false,
+                version: None,
+            }],
+        );
+
+        let Err(DependencyError::Cyclic(cycle)) = sort_dependencies(vec![pkg1, pkg2, pkg3]) else {
+            panic!("cycle was not detected");
+        };
+
+        assert_eq!(
+            cycle.to_string(),
+            "pkg1 -> pkg2 (load_after of pkg2) -> pkg1 (load_after of pkg1)"
+        );
     }
 
     #[test]
@@ -294,6 +604,7 @@ mod tests {
             vec![Dependent {
                 id: "pkg2".to_owned(),
                 optional: false,
+                version: None,
             }],
             vec![],
         );
@@ -304,10 +615,12 @@ mod tests {
             vec![Dependent {
                 id: "pkg2".to_owned(),
                 optional: false,
+                version: None,
             }],
             vec![Dependent {
                 id: "pkg1".to_owned(),
                 optional: false,
+                version: None,
             }],
         );
         let sorted_pkgs = sort_dependencies(vec![pkg1, pkg2, pkg3]).expect("failed to sort");
@@ -324,6 +637,7 @@ mod tests {
             vec![Dependent {
                 id: "pkg2".to_owned(),
                 optional: false,
+                version: None,
             }],
             vec![],
         );
@@ -336,6 +650,93 @@ mod tests {
         assert_eq!("pkg1", sorted_pkgs[1].id());
     }
 
+    #[test]
+    fn checks_dependency_versions() {
+        let mut fixes = mock_package("fixes", vec![], vec![]);
+        fixes.metadata.version = Some("1.1.0".parse().unwrap());
+
+        let dependent = |version: &str| {
+            mock_package(
+                "overhaul",
+                vec![Dependent {
+                    id: "fixes".to_owned(),
+                    optional: true,
+                    version: Some(version.parse().unwrap()),
+                }],
+                vec![],
+            )
+        };
+
+        let sorted = sort_dependencies(vec![dependent(">=1.0, <2"), fixes.clone()]).unwrap();
+        assert_eq!("fixes", sorted[0].id());
+
+        let error = sort_dependencies(vec![dependent(">=1.2"), fixes.clone()]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "overhaul requires fixes >=1.2, but version 1.1.0 is loaded"
+        );
+
+        fixes.metadata.version = None;
+
+        assert!(matches!(
+            sort_dependencies(vec![dependent("^1"), fixes]),
+            Err(DependencyError::UnknownVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn checks_requirements_and_conflicts() {
+        let relation = |id: &str, version: Option<&str>| Relation {
+            id: id.to_owned(),
+            version: version.map(|v| v.parse().unwrap()),
+        };
+
+        let mut overhaul = mock_package("overhaul", vec![], vec![]);
+        overhaul.requires = vec![relation("fixes", Some("^1"))];
+        overhaul.conflicts_with = vec![
+            relation("other-overhaul", None),
+            relation("old", Some("<2")),
+        ];
+
+        let mut fixes = mock_package("fixes", vec![], vec![]);
+        fixes.metadata.version = Some("1.4.0".parse().unwrap());
+
+        let other = mock_package("other-overhaul", vec![], vec![]);
+
+        let mut old = mock_package("old", vec![], vec![]);
+        old.metadata.version = Some("2.0.0".parse().unwrap());
+
+        sort_dependencies(vec![overhaul.clone(), fixes.clone(), old.clone()]).unwrap();
+
+        assert!(matches!(
+            sort_dependencies(vec![overhaul.clone(), old.clone()]),
+            Err(DependencyError::MissingRequirement { id, .. }) if id == "fixes"
+        ));
+
+        let mut disabled_fixes = fixes.clone();
+        disabled_fixes.enabled = false;
+
+        assert!(matches!(
+            sort_dependencies(vec![overhaul.clone(), disabled_fixes]),
+            Err(DependencyError::MissingRequirement { .. })
+        ));
+
+        assert!(matches!(
+            sort_dependencies(vec![overhaul.clone(), fixes.clone(), other.clone()]),
+            Err(DependencyError::Conflict { id, .. }) if id == "other-overhaul"
+        ));
+
+        old.metadata.version = Some("1.0.0".parse().unwrap());
+
+        assert!(matches!(
+            sort_dependencies(vec![overhaul.clone(), fixes.clone(), old]),
+            Err(DependencyError::Conflict { id, .. }) if id == "old"
+        ));
+
+        overhaul.enabled = false;
+        sort_dependencies(vec![overhaul, other]).unwrap();
+    }
+
     #[test]
     fn smoke_test() {
         let pkg1 = mock_package("pkg1", vec![], vec![]);
@@ -355,6 +756,7 @@ mod tests {
             vec![Dependent {
                 id: "pkg2".to_owned(),
                 optional: false,
+                version: None,
             }],
         );
         let pkg4 = mock_package(
@@ -363,6 +765,7 @@ mod tests {
             vec![Dependent {
                 id: "pkg2".to_owned(),
                 optional: false,
+                version: None,
             }],
         );
         let pkg5 = mock_package("pkg5", vec![], vec![]);
@@ -0,0 +1,115 @@
+use schemars::JsonSchema;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dependency::{Dependency, Dependent},
+    metadata::Metadata,
+    package::{ModFile, Package, WithPackageSource},
+};
+
+fn on() -> bool {
+    true
+}
+
+/// A single file that overrides an asset at a specific path within the DVDBND, regardless of the
+/// file's own name.
+///
+/// File mappings share IDs, ordering and conflict detection with packages and are loaded after
+/// the packages listed in the same profile unless ordered otherwise.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct FileMapping {
+    /// The unique identifier for this file mapping. Defaults to the source path.
+    #[serde(default)]
+    pub(crate) id: Option<String>,
+
+    /// Enable this file mapping?
+    #[serde(default = "on")]
+    pub enabled: bool,
+
+    /// A path to the file that will be served. Can be relative to the mod profile.
+    #[serde(alias = "path")]
+    pub(crate) source: ModFile,
+
+    /// The asset path the file will be served as, e.g. `data0:/regulation.bin`. Without a mount
+    /// root the file is served for every root.
+    pub(crate) target: String,
+
+    /// A list of package or file mapping IDs that this file mapping should load after.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) load_after: Vec<Dependent<String>>,
+
+    /// A list of package or file mapping IDs that this file mapping should load before.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) load_before: Vec<Dependent<String>>,
+
+    /// Descriptive information about this file mapping.
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+}
+
+impl FileMapping {
+    /// The asset path the file will be served as.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+}
+
+impl WithPackageSource for FileMapping {
+    fn source(&self) -> &ModFile {
+        &self.source
+    }
+
+    fn source_mut(&mut self) -> &mut ModFile {
+        &mut self.source
+    }
+}
+
+impl Dependency for FileMapping {
+    type UniqueId = String;
+
+    fn id(&self) -> Self::UniqueId {
+        self.id
+            .clone()
+            .unwrap_or_else(|| self.source.to_string_lossy().into())
+    }
+
+    fn version(&self) -> Option<&Version> {
+        self.metadata.version.as_ref()
+    }
+
+    fn loads_after(&self) -> &[Dependent<Self::UniqueId>] {
+        &self.load_after
+    }
+
+    fn loads_before(&self) -> &[Dependent<Self::UniqueId>] {
+        &self.load_before
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl From<FileMapping> for Package {
+    fn from(file: FileMapping) -> Self {
+        let id = file.id();
+
+        Self {
+            id: Some(id),
+            enabled: file.enabled,
+            path: file.source,
+            load_after: file.load_after,
+            load_before: file.load_before,
+            requires: vec![],
+            conflicts_with: vec![],
+            roots: vec![],
+            target: Some(file.target),
+            include: vec![],
+            exclude: vec![],
+            mount_point: None,
+            hide: vec![],
+            metadata: file.metadata,
+        }
+    }
+}
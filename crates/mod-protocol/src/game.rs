@@ -1,19 +1,27 @@
 use std::{
     fmt::Display,
-    path::{Path, PathBuf},
+    iter,
+    path::PathBuf,
+    slice,
+    sync::{LazyLock, OnceLock},
 };
 
 use schemars::{json_schema, JsonSchema};
-use serde::{de::Error, Deserialize, Serialize};
+use serde::{de::Error as _, Deserialize, Serialize};
 use serde_json::json;
 use strum::VariantArray;
-use strum_macros::VariantArray;
+use strum_macros::{IntoStaticStr, VariantArray};
+use thiserror::Error;
 
-/// Chronologically sorted list of games supported by me3.
+/// Chronologically sorted list of engine generations supported by me3.
 ///
-/// Feature gates can use [`Ord`] comparisons between game type constants.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, VariantArray)]
+/// Every [`GameDefinition`] names the generation it runs on, which decides the hooks and fixes that
+/// are applied to it. Feature gates can use [`Ord`] comparisons between game type constants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, VariantArray, IntoStaticStr)]
+#[strum(serialize_all = "lowercase")]
 pub enum Game {
+    DarkSoulsRemastered,
+    DarkSouls2,
     DarkSouls3,
     Sekiro,
     EldenRing,
@@ -22,86 +30,40 @@ pub enum Game {
 }
 
 impl Game {
-    /// The AppID of the Steam compatibility tool that was used to verify this game on Steam Deck.
-    pub const fn verified_on_deck_runtime(self) -> Option<&'static str> {
-        use Game::*;
-        // TODO: we need a better way to deal with this.
-        const PROTON_STABLE: &str = "proton_10";
-        match self {
-            DarkSouls3 => Some("proton_8"),
-            Sekiro => Some(PROTON_STABLE),
-            EldenRing => Some("proton_8"),
-            ArmoredCore6 => Some("proton_8"),
-            Nightreign => Some("proton_9"),
-        }
+    /// The primary name of a game as a lowercase string.
+    pub fn name(self) -> &'static str {
+        self.into()
     }
 
-    /// The primary name of a game as a lowercase string.
-    pub const fn name(self) -> &'static str {
-        use Game::*;
-        match self {
-            DarkSouls3 => "darksouls3",
-            Sekiro => "sekiro",
-            EldenRing => "eldenring",
-            ArmoredCore6 => "armoredcore6",
-            Nightreign => "nightreign",
-        }
+    /// The built-in definition of the game this engine generation is named after.
+    pub fn definition(self) -> &'static GameDefinition {
+        GameDefinitions::builtin()
+            .for_engine(self)
+            .expect("built-in games cover every engine")
     }
 
     /// The full, official name of a game.
-    pub const fn title(self) -> &'static str {
-        use Game::*;
-        match self {
-            DarkSouls3 => "Dark Souls III",
-            Sekiro => "Sekiro: Shadows Die Twice",
-            EldenRing => "Elden Ring",
-            ArmoredCore6 => "Armored Core VI: Fires of Rubicon",
-            Nightreign => "Elden Ring Nightreign",
-        }
+    pub fn title(self) -> &'static str {
+        &self.definition().title
     }
 
     /// All names and aliases of a game as lowercase strings, including the primary name.
-    pub fn possible_names(self) -> &'static [&'static str] {
-        use Game::*;
-        match self {
-            DarkSouls3 => &[const { DarkSouls3.name() }, "ds3"],
-            Sekiro => &[const { Sekiro.name() }, "sdt"],
-            EldenRing => &[const { EldenRing.name() }, "er", "elden-ring"],
-            ArmoredCore6 => &[const { ArmoredCore6.name() }, "ac6"],
-            Nightreign => &[const { Nightreign.name() }, "nr", "nightrein"],
-        }
+    pub fn possible_names(self) -> impl Iterator<Item = &'static str> {
+        self.definition().names()
     }
 
     /// All aliases of a game as lowercase strings, excluding the primary name.
-    pub fn aliases(self) -> &'static [&'static str] {
-        &self.possible_names()[1..]
-    }
-
-    /// The savefile directory used by a game.
-    pub fn savefile_dir(self) -> Option<PathBuf> {
-        use Game::*;
-
-        // DS1/DSR will need to resolve CSIDL_MYDOCUMENTS/FOLDERID_Documents instead.
-        let base_dir = match self {
-            DarkSouls3 | Sekiro | EldenRing | ArmoredCore6 | Nightreign => {
-                std::env::var_os("APPDATA").map(PathBuf::from)
-            }
-        };
-
-        Some(match self {
-            DarkSouls3 => base_dir?.join("DarkSoulsIII"),
-            Sekiro => base_dir?.join("Sekiro"),
-            EldenRing => base_dir?.join("EldenRing"),
-            ArmoredCore6 => base_dir?.join("ArmoredCore6"),
-            Nightreign => base_dir?.join("Nightreign"),
-        })
+    pub fn aliases(self) -> impl Iterator<Item = &'static str> {
+        self.possible_names().skip(1)
     }
 
     fn to_json(self) -> serde_json::Value {
+        let definition = self.definition();
+
         json!({
-            "description": format!("{} (Steam App ID: {})", self.title(), self.app_id()),
-            "enum": self.possible_names(),
-            "title": self.title()
+            "description": format!("{} (Steam App ID: {})", definition.title, definition.app_id),
+            "enum": self.possible_names().collect::<Vec<_>>(),
+            "title": definition.title
         })
     }
 }
@@ -118,45 +80,263 @@ impl TryFrom<String> for Game {
     fn try_from(mut name: String) -> Result<Self, Self::Error> {
         name.make_ascii_lowercase();
 
-        Self::VARIANTS
-            .iter()
-            .copied()
-            .find(|game| game.possible_names().contains(&&*name))
+        // Engine names are checked first, as the game tables refer to them.
+        if let Some(game) = Self::VARIANTS.iter().find(|game| game.name() == name) {
+            return Ok(*game);
+        }
+
+        GameDefinitions::configured()
+            .find(&name)
+            .map(|game| game.engine)
             .ok_or(InvalidGame(name))
     }
 }
 
-impl Game {
-    /// Returns the Steam App ID of a game.
-    pub fn app_id(self) -> u32 {
-        use Game::*;
-        match self {
-            DarkSouls3 => 374320,
-            Sekiro => 814380,
-            EldenRing => 1245620,
-            ArmoredCore6 => 1888160,
-            Nightreign => 2622380,
+/// A game me3 can launch, defined by the built-in game table or by the `[games.<name>]` tables of
+/// me3.toml.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameDefinition {
+    /// The primary name of the game as a lowercase string.
+    pub name: String,
+
+    /// The full, official name of the game.
+    pub title: String,
+
+    /// Other names of the game as lowercase strings, excluding the primary name.
+    pub aliases: Vec<String>,
+
+    /// The engine generation the game runs on.
+    pub engine: Game,
+
+    /// The Steam App ID of the game.
+    pub app_id: u32,
+
+    /// The path to the game's executable in its Steam installation folder.
+    pub executable: PathBuf,
+
+    /// The directory containing the game's savefiles. `${DOCUMENTS_DIR}` is replaced with the
+    /// user's Documents folder and other `${NAME}`s with the environment variable `NAME` of the
+    /// game process.
+    pub savefile_dir: Option<PathBuf>,
+
+    /// The AppID of the Steam compatibility tool that was used to verify the game on Steam Deck.
+    pub deck_runtime: Option<String>,
+}
+
+impl GameDefinition {
+    /// All names and aliases of the game, including the primary name.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        iter::once(&self.name)
+            .chain(&self.aliases)
+            .map(String::as_str)
+    }
+
+    /// Whether `name` is the name or one of the aliases of the game, ignoring case.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.names().any(|known| known.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A game as written in the built-in game table or in a `[games.<name>]` table of me3.toml.
+///
+/// Fields that are left out are taken from the game with the same name if there is one, and
+/// otherwise from the built-in game of the same engine generation, except for the title and
+/// aliases.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct GameEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aliases: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine: Option<Game>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executable: Option<PathBuf>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub savefile_dir: Option<PathBuf>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deck_runtime: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum GameDefinitionError {
+    #[error("game {name} does not set `{field}`, and there is no game to take it from")]
+    MissingField { name: String, field: &'static str },
+
+    #[error("{alias} is used as a name of both {first} and {second}")]
+    DuplicateName {
+        alias: String,
+        first: String,
+        second: String,
+    },
+}
+
+/// The games me3 can launch, in the order they were defined.
+#[derive(Clone, Debug)]
+pub struct GameDefinitions {
+    games: Vec<GameDefinition>,
+}
+
+static BUILTIN_GAMES: LazyLock<GameDefinitions> = LazyLock::new(|| {
+    let table: toml::Table =
+        toml::from_str(include_str!("games.toml")).expect("built-in game table is valid TOML");
+
+    let mut games = GameDefinitions { games: vec![] };
+
+    for (name, entry) in table {
+        let entry = entry.try_into().expect("built-in game entries are valid");
+        games
+            .insert(&name, entry)
+            .expect("built-in games are complete");
+    }
+
+    games
+});
+
+static CONFIGURED_GAMES: OnceLock<GameDefinitions> = OnceLock::new();
+
+impl GameDefinitions {
+    /// The games built into me3.
+    pub fn builtin() -> &'static Self {
+        &BUILTIN_GAMES
+    }
+
+    /// The games that names in profiles are resolved with, the built-in games unless other games
+    /// were [configured](Self::configure).
+    pub fn configured() -> &'static Self {
+        CONFIGURED_GAMES.get().unwrap_or(Self::builtin())
+    }
+
+    /// Resolve game names in profiles with these games, so that profiles can name games defined
+    /// in me3.toml. Those names resolve to the engine generation of the game. Only the first call
+    /// has an effect.
+    pub fn configure(self) {
+        let _ = CONFIGURED_GAMES.set(self);
+    }
+
+    /// The built-in games, extended or overridden by `entries`.
+    pub fn with_entries<'a>(
+        entries: impl IntoIterator<Item = (&'a str, &'a GameEntry)>,
+    ) -> Result<Self, GameDefinitionError> {
+        let mut games = Self::builtin().clone();
+
+        for (name, entry) in entries {
+            games.insert(name, entry.clone())?;
+        }
+
+        Ok(games)
+    }
+
+    /// Defines a new game, or overrides the fields set in `entry` if a game named `name` exists.
+    pub fn insert(
+        &mut self,
+        name: &str,
+        entry: GameEntry,
+    ) -> Result<&GameDefinition, GameDefinitionError> {
+        let name = name.to_ascii_lowercase();
+        let existing = self.games.iter().position(|game| game.name == name);
+
+        let base = match existing {
+            Some(index) => Some(&self.games[index]),
+            None => entry.engine.and_then(|engine| self.find(engine.name())),
+        };
+
+        let missing = |field| GameDefinitionError::MissingField {
+            name: name.clone(),
+            field,
+        };
+
+        let definition = GameDefinition {
+            title: entry
+                .title
+                .or_else(|| existing.and(base).map(|base| base.title.clone()))
+                .unwrap_or_else(|| name.clone()),
+            aliases: entry
+                .aliases
+                .map(|aliases| {
+                    aliases
+                        .iter()
+                        .map(|alias| alias.to_ascii_lowercase())
+                        .collect()
+                })
+                .or_else(|| existing.and(base).map(|base| base.aliases.clone()))
+                .unwrap_or_default(),
+            engine: entry
+                .engine
+                .or(base.map(|base| base.engine))
+                .ok_or_else(|| missing("engine"))?,
+            app_id: entry
+                .app_id
+                .or(base.map(|base| base.app_id))
+                .ok_or_else(|| missing("app-id"))?,
+            executable: entry
+                .executable
+                .or_else(|| base.map(|base| base.executable.clone()))
+                .ok_or_else(|| missing("executable"))?,
+            savefile_dir: entry
+                .savefile_dir
+                .or_else(|| base.and_then(|base| base.savefile_dir.clone())),
+            deck_runtime: entry
+                .deck_runtime
+                .or_else(|| base.and_then(|base| base.deck_runtime.clone())),
+            name,
+        };
+
+        for alias in definition.names() {
+            let other = self
+                .games
+                .iter()
+                .find(|game| game.name != definition.name && game.is_named(alias));
+
+            if let Some(other) = other {
+                return Err(GameDefinitionError::DuplicateName {
+                    alias: alias.to_owned(),
+                    first: other.name.clone(),
+                    second: definition.name.clone(),
+                });
+            }
         }
+
+        let index = match existing {
+            Some(index) => {
+                self.games[index] = definition;
+                index
+            }
+            None => {
+                self.games.push(definition);
+                self.games.len() - 1
+            }
+        };
+
+        Ok(&self.games[index])
     }
 
-    /// Returns a game from its Steam App ID.
-    pub fn from_app_id(id: u32) -> Option<Self> {
-        Self::VARIANTS
-            .iter()
-            .copied()
-            .find(|game| game.app_id() == id)
+    /// Finds a game by its name or one of its aliases, ignoring case.
+    pub fn find(&self, name: &str) -> Option<&GameDefinition> {
+        self.games.iter().find(|game| game.is_named(name))
     }
 
-    /// Returns the path to a game's executable in its Steam installation folder.
-    pub fn executable(self) -> &'static Path {
-        use Game::*;
-        Path::new(match self {
-            DarkSouls3 => "Game/DarkSoulsIII.exe",
-            Sekiro => "sekiro.exe",
-            EldenRing => "Game/eldenring.exe",
-            ArmoredCore6 => "Game/armoredcore6.exe",
-            Nightreign => "Game/nightreign.exe",
-        })
+    /// Finds the first game with the given Steam App ID.
+    pub fn from_app_id(&self, app_id: u32) -> Option<&GameDefinition> {
+        self.games.iter().find(|game| game.app_id == app_id)
+    }
+
+    /// The game an engine generation is named after.
+    pub fn for_engine(&self, engine: Game) -> Option<&GameDefinition> {
+        self.find(engine.name())
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, GameDefinition> {
+        self.games.iter()
     }
 }
 
@@ -207,3 +387,90 @@ impl JsonSchema for Game {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use strum::VariantArray;
+
+    use super::{Game, GameDefinitionError, GameDefinitions, GameEntry};
+    use crate::Supports;
+
+    #[test]
+    fn builtin_games_cover_every_engine() {
+        for &engine in Game::VARIANTS {
+            let definition = engine.definition();
+
+            assert_eq!(definition.name, engine.name());
+            assert_eq!(definition.engine, engine);
+            assert_eq!(
+                Game::try_from(engine.name().to_uppercase()).ok(),
+                Some(engine)
+            );
+        }
+
+        let eldenring = GameDefinitions::builtin().from_app_id(1245620).unwrap();
+        assert_eq!(eldenring.engine, Game::EldenRing);
+        assert_eq!(Game::try_from("ER".to_owned()).ok(), Some(Game::EldenRing));
+    }
+
+    #[test]
+    fn entries_extend_builtin_games() {
+        let entry = |toml: &str| toml::from_str::<GameEntry>(toml).unwrap();
+
+        let beta = entry(
+            r#"
+            engine = "er"
+            aliases = ["er-beta"]
+            executable = "Game/eldenring_beta.exe"
+            "#,
+        );
+        let sekiro = entry(r#"deck-runtime = "proton_9""#);
+
+        let games = GameDefinitions::with_entries([("eldenring-beta", &beta), ("Sekiro", &sekiro)])
+            .unwrap();
+
+        let beta = games.find("ER-BETA").unwrap();
+        assert_eq!(beta.name, "eldenring-beta");
+        assert_eq!(beta.title, "eldenring-beta");
+        assert_eq!(beta.engine, Game::EldenRing);
+        assert_eq!(beta.app_id, Game::EldenRing.definition().app_id);
+        assert_eq!(beta.executable, Path::new("Game/eldenring_beta.exe"));
+
+        let sekiro = games.for_engine(Game::Sekiro).unwrap();
+        assert_eq!(sekiro.deck_runtime.as_deref(), Some("proton_9"));
+        assert_eq!(sekiro.title, Game::Sekiro.title());
+        assert_eq!(
+            games.iter().count(),
+            GameDefinitions::builtin().iter().count() + 1
+        );
+
+        assert!(matches!(
+            GameDefinitions::with_entries([("new", &entry(r#"app-id = 1"#))]),
+            Err(GameDefinitionError::MissingField {
+                field: "engine",
+                ..
+            })
+        ));
+        let clashing = entry("engine = \"nr\"\naliases = [\"nr\"]");
+        assert!(matches!(
+            GameDefinitions::with_entries([("nr-beta", &clashing)]),
+            Err(GameDefinitionError::DuplicateName { alias, .. }) if alias == "nr"
+        ));
+    }
+
+    #[test]
+    fn profiles_name_configured_games() {
+        let supports = |game: &str| toml::from_str::<Supports>(&format!("game = \"{game}\""));
+
+        assert!(supports("ds3-custom").is_err());
+
+        let entry = toml::from_str::<GameEntry>(r#"engine = "ds3""#).unwrap();
+        GameDefinitions::with_entries([("ds3-custom", &entry)])
+            .unwrap()
+            .configure();
+
+        assert_eq!(supports("DS3-Custom").unwrap().game, Game::DarkSouls3);
+    }
+}
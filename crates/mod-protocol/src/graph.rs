@@ -0,0 +1,353 @@
+//! Rendering of the load order and the relations between natives and packages as a graph.
+
+use std::fmt::Write;
+
+use crate::dependency::Dependency;
+
+/// The kind of relation an edge in a [`DependencyGraph`] was declared with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    LoadBefore,
+    LoadAfter,
+    Requires,
+    ConflictsWith,
+}
+
+impl EdgeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeKind::LoadBefore => "load_before",
+            EdgeKind::LoadAfter => "load_after",
+            EdgeKind::Requires => "requires",
+            EdgeKind::ConflictsWith => "conflicts_with",
+        }
+    }
+}
+
+struct GraphGroup {
+    name: String,
+    /// IDs of the items in the group, in load order.
+    ids: Vec<String>,
+}
+
+/// A relation declared by an item, identified by its group and position in it.
+struct GraphRelation {
+    from: (usize, usize),
+    id: String,
+    kind: EdgeKind,
+}
+
+/// An edge between two items, identified by their group and position in it.
+///
+/// Ordering edges point from the item that loads first to the item that loads after it, other
+/// edges point from the item that declares the relation.
+struct GraphEdge {
+    from: (usize, usize),
+    to: (usize, usize),
+    kind: EdgeKind,
+}
+
+/// Items in load order and the relations declared between them.
+#[derive(Default)]
+pub struct DependencyGraph {
+    groups: Vec<GraphGroup>,
+    relations: Vec<GraphRelation>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a group of items, e.g. the natives of a profile, in load order. Relations can connect
+    /// items in different groups, relations to items that aren't loaded are left out.
+    pub fn add_group<T: Dependency<UniqueId = String>>(&mut self, name: &str, items: &[T]) {
+        let group = self.groups.len();
+
+        for (index, item) in items.iter().enumerate() {
+            let ordering = item
+                .loads_before()
+                .iter()
+                .map(|dep| (dep.id(), EdgeKind::LoadBefore))
+                .chain(
+                    item.loads_after()
+                        .iter()
+                        .map(|dep| (dep.id(), EdgeKind::LoadAfter)),
+                );
+
+            let relations = item
+                .requires()
+                .iter()
+                .map(|rel| (rel.id(), EdgeKind::Requires))
+                .chain(
+                    item.conflicts_with()
+                        .iter()
+                        .map(|rel| (rel.id(), EdgeKind::ConflictsWith)),
+                );
+
+            for (id, kind) in ordering.chain(relations) {
+                self.relations.push(GraphRelation {
+                    from: (group, index),
+                    id,
+                    kind,
+                });
+            }
+        }
+
+        self.groups.push(GraphGroup {
+            name: name.to_owned(),
+            ids: items.iter().map(Dependency::id).collect(),
+        });
+    }
+
+    /// Resolves the declared relations to the items they refer to.
+    fn edges(&self) -> Vec<GraphEdge> {
+        let position = |id: &String| {
+            self.groups
+                .iter()
+                .enumerate()
+                .find_map(|(group, GraphGroup { ids, .. })| {
+                    ids.iter()
+                        .position(|other| other == id)
+                        .map(|index| (group, index))
+                })
+        };
+
+        self.relations
+            .iter()
+            .filter_map(|GraphRelation { from, id, kind }| {
+                let other = position(id)?;
+
+                let (from, to) = match kind {
+                    EdgeKind::LoadAfter => (other, *from),
+                    _ => (*from, other),
+                };
+
+                Some(GraphEdge {
+                    from,
+                    to,
+                    kind: *kind,
+                })
+            })
+            .collect()
+    }
+
+    /// Renders the graph in the Graphviz DOT language.
+    pub fn to_dot(&self) -> String {
+        let mut out =
+            String::from("digraph load_order {\n    rankdir=LR;\n    node [shape=box];\n");
+
+        for (group, GraphGroup { name, ids }) in self.groups.iter().enumerate() {
+            if ids.is_empty() {
+                continue;
+            }
+
+            let _ = writeln!(out, "\n    subgraph cluster_{group} {{");
+            let _ = writeln!(out, "        label={};", dot_string(name));
+
+            for (index, id) in ids.iter().enumerate() {
+                let label = format!("{}. {id}", index + 1);
+                let _ = writeln!(
+                    out,
+                    "        n{group}_{index} [label={}];",
+                    dot_string(&label)
+                );
+            }
+
+            out.push_str("    }\n");
+        }
+
+        let edges = self.edges();
+
+        if !edges.is_empty() {
+            out.push('\n');
+        }
+
+        for GraphEdge { from, to, kind } in &edges {
+            let style = match kind {
+                EdgeKind::LoadBefore | EdgeKind::LoadAfter => "",
+                EdgeKind::Requires => ", style=dashed",
+                EdgeKind::ConflictsWith => ", style=dashed, color=red, dir=none",
+            };
+
+            let _ = writeln!(
+                out,
+                "    n{}_{} -> n{}_{} [label=\"{}\"{style}];",
+                from.0,
+                from.1,
+                to.0,
+                to.1,
+                kind.as_str()
+            );
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the graph as a Mermaid flowchart.
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart LR\n");
+
+        for (group, GraphGroup { name, ids }) in self.groups.iter().enumerate() {
+            if ids.is_empty() {
+                continue;
+            }
+
+            let _ = writeln!(out, "    subgraph g{group} [{}]", mermaid_string(name));
+
+            for (index, id) in ids.iter().enumerate() {
+                let label = format!("{}. {id}", index + 1);
+                let _ = writeln!(out, "        n{group}_{index}[{}]", mermaid_string(&label));
+            }
+
+            out.push_str("    end\n");
+        }
+
+        for GraphEdge { from, to, kind } in &self.edges() {
+            let (start, end) = match kind {
+                EdgeKind::LoadBefore | EdgeKind::LoadAfter => ("--", "-->"),
+                EdgeKind::Requires => ("-.", ".->"),
+                EdgeKind::ConflictsWith => ("-.", ".-"),
+            };
+
+            let _ = writeln!(
+                out,
+                "    n{}_{} {start} {} {end} n{}_{}",
+                from.0,
+                from.1,
+                kind.as_str(),
+                to.0,
+                to.1
+            );
+        }
+
+        out
+    }
+}
+
+fn dot_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn mermaid_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "#quot;"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DependencyGraph;
+    use crate::{native::Native, package::Package};
+
+    fn packages(source: &str) -> Vec<Package> {
+        #[derive(serde::Deserialize)]
+        struct Packages {
+            packages: Vec<Package>,
+        }
+
+        toml::from_str::<Packages>(source).unwrap().packages
+    }
+
+    #[test]
+    fn renders_relations() {
+        let packages = packages(
+            r#"
+[[packages]]
+id = "base"
+path = "base"
+
+[[packages]]
+id = "overhaul"
+path = "overhaul"
+load_after = [{ id = "base", optional = false }]
+requires = [{ id = "base" }]
+conflicts_with = [{ id = "other" }]
+
+[[packages]]
+id = "say \"hi\""
+path = "hi"
+load_before = [{ id = "overhaul", optional = true }]
+"#,
+        );
+
+        let mut graph = DependencyGraph::new();
+        graph.add_group("Packages", &packages);
+
+        assert_eq!(
+            graph.to_mermaid(),
+            r#"flowchart LR
+    subgraph g0 ["Packages"]
+        n0_0["1. base"]
+        n0_1["2. overhaul"]
+        n0_2["3. say #quot;hi#quot;"]
+    end
+    n0_0 -- load_after --> n0_1
+    n0_1 -. requires .-> n0_0
+    n0_2 -- load_before --> n0_1
+"#
+        );
+
+        assert_eq!(
+            graph.to_dot(),
+            r#"digraph load_order {
+    rankdir=LR;
+    node [shape=box];
+
+    subgraph cluster_0 {
+        label="Packages";
+        n0_0 [label="1. base"];
+        n0_1 [label="2. overhaul"];
+        n0_2 [label="3. say \"hi\""];
+    }
+
+    n0_0 -> n0_1 [label="load_after"];
+    n0_1 -> n0_0 [label="requires", style=dashed];
+    n0_2 -> n0_1 [label="load_before"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn connects_groups() {
+        #[derive(serde::Deserialize)]
+        struct Natives {
+            natives: Vec<Native>,
+        }
+
+        let natives = toml::from_str::<Natives>(
+            r#"
+[[natives]]
+path = "mod.dll"
+load_after = [{ id = "assets", optional = false }]
+"#,
+        )
+        .unwrap()
+        .natives;
+
+        let packages = packages(
+            r#"
+[[packages]]
+id = "assets"
+path = "assets"
+"#,
+        );
+
+        let mut graph = DependencyGraph::new();
+        graph.add_group("Natives", &natives);
+        graph.add_group("Packages", &packages);
+
+        assert_eq!(
+            graph.to_mermaid(),
+            r#"flowchart LR
+    subgraph g0 ["Natives"]
+        n0_0["1. mod.dll"]
+    end
+    subgraph g1 ["Packages"]
+        n1_0["1. assets"]
+    end
+    n1_0 -- load_after --> n0_0
+"#
+        );
+    }
+}
@@ -1,22 +1,39 @@
-use std::{fs::File, io::Read, path::Path};
-
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use dependency::Dependency;
+use file::FileMapping;
 use native::Native;
-use package::Package;
+use package::{Package, WithPackageSource};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+pub mod conflicts;
 pub mod dependency;
+pub mod file;
 pub mod game;
+pub mod graph;
+pub mod load_order;
+pub mod metadata;
 pub mod native;
 pub mod package;
+pub mod validation;
+pub mod variables;
 
 pub use game::Game;
 
-#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(tag = "profileVersion")]
 pub enum ModProfile {
     #[serde(rename = "v1")]
     V1(ModProfileV1),
+
+    #[serde(rename = "v2")]
+    V2(ModProfileV2),
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
@@ -50,75 +67,218 @@ impl ModProfile {
         }
     }
 
+    pub fn to_file(&self, path: &Path) -> Result<(), std::io::Error> {
+        let contents = match path.extension().and_then(|path| path.to_str()) {
+            Some("toml") | Some("me3") | None => {
+                toml::to_string_pretty(self).map_err(std::io::Error::other)?
+            }
+            Some("json") => serde_json::to_string_pretty(self).map_err(std::io::Error::other)?,
+            Some(format) => return Err(std::io::Error::other(format!("{format} is unsupported"))),
+        };
+
+        std::fs::write(path, contents)
+    }
+
     pub fn natives_mut(&mut self) -> &mut Vec<Native> {
         match self {
             ModProfile::V1(v1) => &mut v1.natives,
+            ModProfile::V2(v2) => &mut v2.natives,
         }
     }
 
     pub fn packages_mut(&mut self) -> &mut Vec<Package> {
         match self {
             ModProfile::V1(v1) => &mut v1.packages,
+            ModProfile::V2(v2) => &mut v2.packages,
+        }
+    }
+
+    pub fn files_mut(&mut self) -> &mut Vec<FileMapping> {
+        match self {
+            ModProfile::V1(v1) => &mut v1.files,
+            ModProfile::V2(v2) => &mut v2.files,
         }
     }
 
     pub fn supports_mut(&mut self) -> &mut Vec<Supports> {
         match self {
             ModProfile::V1(v1) => &mut v1.supports,
+            ModProfile::V2(v2) => &mut v2.supports,
         }
     }
 
     pub fn start_online_mut(&mut self) -> &mut Option<bool> {
         match self {
             ModProfile::V1(v1) => &mut v1.start_online,
+            ModProfile::V2(v2) => &mut v2.start_online,
         }
     }
 
     pub fn supports(&self) -> Vec<Supports> {
         match self {
             ModProfile::V1(v1) => v1.supports.to_vec(),
+            ModProfile::V2(v2) => v2.supports.to_vec(),
         }
     }
 
     pub fn natives(&self) -> Vec<Native> {
         match self {
             ModProfile::V1(v1) => v1.natives.to_vec(),
+            ModProfile::V2(v2) => v2.natives.to_vec(),
         }
     }
 
     pub fn packages(&self) -> Vec<Package> {
         match self {
             ModProfile::V1(v1) => v1.packages.to_vec(),
+            ModProfile::V2(v2) => v2.packages.to_vec(),
+        }
+    }
+
+    pub fn files(&self) -> Vec<FileMapping> {
+        match self {
+            ModProfile::V1(v1) => v1.files.to_vec(),
+            ModProfile::V2(v2) => v2.files.to_vec(),
         }
     }
 
     pub fn savefile(&self) -> Option<String> {
         match self {
             ModProfile::V1(v1) => v1.savefile.clone(),
+            ModProfile::V2(v2) => v2.savefile.clone(),
         }
     }
 
     pub fn start_online(&self) -> Option<bool> {
         match self {
             ModProfile::V1(v1) => v1.start_online,
+            ModProfile::V2(v2) => v2.start_online,
         }
     }
 
     pub fn disable_arxan(&self) -> Option<bool> {
         match self {
             ModProfile::V1(v1) => v1.disable_arxan,
+            ModProfile::V2(v2) => v2.disable_arxan,
         }
     }
 
     pub fn patch_mem(&self) -> Option<bool> {
         match self {
             ModProfile::V1(v1) => v1.patch_mem,
+            ModProfile::V2(v2) => v2.patch_mem,
+        }
+    }
+
+    /// Paths to the profiles this profile inherits from, in order of increasing precedence.
+    pub fn extends(&self) -> Vec<PathBuf> {
+        match self {
+            ModProfile::V1(v1) => v1.extends.to_vec(),
+            ModProfile::V2(v2) => v2.extends.to_vec(),
+        }
+    }
+
+    /// Makes the sources of all natives, packages and files in this profile absolute using a given
+    /// base directory (this is usually the mod profile's parent path).
+    pub fn make_absolute(&mut self, base: &Path) {
+        fn make_absolute<S: WithPackageSource>(base: &Path, sources: &mut [S]) {
+            sources
+                .iter_mut()
+                .for_each(|source| source.source_mut().make_absolute(base));
+        }
+
+        match self {
+            ModProfile::V1(v1) => {
+                make_absolute(base, &mut v1.natives);
+                make_absolute(base, &mut v1.packages);
+                make_absolute(base, &mut v1.files);
+            }
+            ModProfile::V2(v2) => {
+                make_absolute(base, &mut v2.natives);
+                make_absolute(base, &mut v2.packages);
+                make_absolute(base, &mut v2.files);
+
+                for section in v2.games.values_mut() {
+                    make_absolute(base, &mut section.natives);
+                    make_absolute(base, &mut section.packages);
+                    make_absolute(base, &mut section.files);
+                }
+            }
+        }
+    }
+
+    /// Layers this profile on top of `base`, which is a profile listed in `extends`.
+    ///
+    /// - Natives, packages and files from `base` come first. An entry with the same ID as an
+    ///   inherited entry replaces it, keeping the inherited entry's position in the load order.
+    /// - Supported games are merged, with this profile's `since` version taking precedence.
+    /// - Options that are set by this profile take precedence over the ones in `base`.
+    ///
+    /// If either profile is a v2 profile the result is a v2 profile.
+    pub fn inherit(self, base: ModProfile) -> Self {
+        match (self, base) {
+            (ModProfile::V1(this), ModProfile::V1(base)) => ModProfile::V1(this.inherit(base)),
+            (this, base) => {
+                let (ModProfile::V2(this), ModProfile::V2(base)) = (this.upgrade(), base.upgrade())
+                else {
+                    unreachable!("upgrade always returns the latest version");
+                };
+
+                ModProfile::V2(this.inherit(base))
+            }
+        }
+    }
+
+    /// The version of the profile format this profile was written in.
+    pub fn version(&self) -> &'static str {
+        match self {
+            ModProfile::V1(_) => "v1",
+            ModProfile::V2(_) => "v2",
+        }
+    }
+
+    /// Converts this profile to the latest profile format version.
+    pub fn upgrade(self) -> Self {
+        match self {
+            ModProfile::V1(v1) => ModProfile::V2(v1.into()),
+            v2 @ ModProfile::V2(_) => v2,
+        }
+    }
+
+    /// Merges the section specific to `game` (if any) into the top-level profile contents.
+    ///
+    /// Natives, packages and files from the game section are appended after the shared ones and any
+    /// options set in the game section take precedence over shared options.
+    pub fn for_game(&self, game: Game) -> Self {
+        match self {
+            ModProfile::V1(_) => self.clone(),
+            ModProfile::V2(v2) => {
+                let mut v2 = v2.clone();
+                let games = std::mem::take(&mut v2.games);
+
+                if let Some(section) = games.get(&game) {
+                    v2.natives.extend(section.natives.iter().cloned());
+                    v2.packages.extend(section.packages.iter().cloned());
+                    v2.files.extend(section.files.iter().cloned());
+                    v2.savefile = section.savefile.clone().or(v2.savefile);
+                    v2.start_online = section.start_online.or(v2.start_online);
+                    v2.disable_arxan = section.disable_arxan.or(v2.disable_arxan);
+                    v2.patch_mem = section.patch_mem.or(v2.patch_mem);
+                }
+
+                ModProfile::V2(v2)
+            }
         }
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
 pub struct ModProfileV1 {
+    /// Profiles to inherit natives, packages, supported games and options from. Paths can be
+    /// relative to the mod profile or names of profiles in the profile directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extends: Vec<PathBuf>,
+
     /// The games that this profile supports.
     #[serde(default)]
     supports: Vec<Supports>,
@@ -134,6 +294,91 @@ pub struct ModProfileV1 {
     #[serde(alias = "package")]
     packages: Vec<Package>,
 
+    /// Single files that override assets at a specific path, loaded after the packages.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    files: Vec<FileMapping>,
+
+    /// Name of an alternative savefile to use (in the default savefile directory).
+    #[serde(default)]
+    savefile: Option<String>,
+
+    /// Starts the game with multiplayer server connectivity enabled.
+    #[serde(default)]
+    start_online: Option<bool>,
+
+    /// Try to neutralize Arxan GuardIT code protection to improve mod stability.
+    #[serde(default)]
+    disable_arxan: Option<bool>,
+
+    /// Patch memory limits for supported games to improve mod stability.
+    #[serde(default)]
+    patch_mem: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ModProfileV2 {
+    /// Profiles to inherit natives, packages, supported games and options from. Paths can be
+    /// relative to the mod profile or names of profiles in the profile directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extends: Vec<PathBuf>,
+
+    /// The games that this profile supports.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    supports: Vec<Supports>,
+
+    /// Native modules (DLLs) that will be loaded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(alias = "native")]
+    natives: Vec<Native>,
+
+    /// A collection of packages containing assets that should be considered for loading
+    /// before the DVDBND.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(alias = "package")]
+    packages: Vec<Package>,
+
+    /// Single files that override assets at a specific path, loaded after the packages.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    files: Vec<FileMapping>,
+
+    /// Name of an alternative savefile to use (in the default savefile directory).
+    #[serde(default)]
+    savefile: Option<String>,
+
+    /// Starts the game with multiplayer server connectivity enabled.
+    #[serde(default)]
+    start_online: Option<bool>,
+
+    /// Try to neutralize Arxan GuardIT code protection to improve mod stability.
+    #[serde(default)]
+    disable_arxan: Option<bool>,
+
+    /// Patch memory limits for supported games to improve mod stability.
+    #[serde(default)]
+    patch_mem: Option<bool>,
+
+    /// Sections that only apply when launching a specific game.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    games: BTreeMap<Game, GameSection>,
+}
+
+/// Natives, packages and options that only apply when launching a specific game.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct GameSection {
+    /// Native modules (DLLs) that will be loaded after the shared natives.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(alias = "native")]
+    natives: Vec<Native>,
+
+    /// Packages that will be loaded after the shared packages.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(alias = "package")]
+    packages: Vec<Package>,
+
+    /// Single files that override assets at a specific path, loaded after the packages.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    files: Vec<FileMapping>,
+
     /// Name of an alternative savefile to use (in the default savefile directory).
     #[serde(default)]
     savefile: Option<String>,
@@ -151,6 +396,119 @@ pub struct ModProfileV1 {
     patch_mem: Option<bool>,
 }
 
+impl From<ModProfileV1> for ModProfileV2 {
+    fn from(v1: ModProfileV1) -> Self {
+        let ModProfileV1 {
+            extends,
+            supports,
+            natives,
+            packages,
+            files,
+            savefile,
+            start_online,
+            disable_arxan,
+            patch_mem,
+        } = v1;
+
+        Self {
+            extends,
+            supports,
+            natives,
+            packages,
+            files,
+            savefile,
+            start_online,
+            disable_arxan,
+            patch_mem,
+            games: BTreeMap::new(),
+        }
+    }
+}
+
+impl ModProfileV1 {
+    fn inherit(self, base: Self) -> Self {
+        Self {
+            extends: self.extends,
+            supports: inherit_supports(self.supports, base.supports),
+            natives: inherit_entries(self.natives, base.natives),
+            packages: inherit_entries(self.packages, base.packages),
+            files: inherit_entries(self.files, base.files),
+            savefile: self.savefile.or(base.savefile),
+            start_online: self.start_online.or(base.start_online),
+            disable_arxan: self.disable_arxan.or(base.disable_arxan),
+            patch_mem: self.patch_mem.or(base.patch_mem),
+        }
+    }
+}
+
+impl ModProfileV2 {
+    fn inherit(self, mut base: Self) -> Self {
+        let mut games = self.games;
+
+        for (game, section) in games.iter_mut() {
+            if let Some(base_section) = base.games.remove(game) {
+                *section = std::mem::take(section).inherit(base_section);
+            }
+        }
+
+        games.extend(base.games);
+
+        Self {
+            extends: self.extends,
+            supports: inherit_supports(self.supports, base.supports),
+            natives: inherit_entries(self.natives, base.natives),
+            packages: inherit_entries(self.packages, base.packages),
+            files: inherit_entries(self.files, base.files),
+            savefile: self.savefile.or(base.savefile),
+            start_online: self.start_online.or(base.start_online),
+            disable_arxan: self.disable_arxan.or(base.disable_arxan),
+            patch_mem: self.patch_mem.or(base.patch_mem),
+            games,
+        }
+    }
+}
+
+impl GameSection {
+    fn inherit(self, base: Self) -> Self {
+        Self {
+            natives: inherit_entries(self.natives, base.natives),
+            packages: inherit_entries(self.packages, base.packages),
+            files: inherit_entries(self.files, base.files),
+            savefile: self.savefile.or(base.savefile),
+            start_online: self.start_online.or(base.start_online),
+            disable_arxan: self.disable_arxan.or(base.disable_arxan),
+            patch_mem: self.patch_mem.or(base.patch_mem),
+        }
+    }
+}
+
+fn inherit_entries<T: Dependency>(entries: Vec<T>, mut inherited: Vec<T>) -> Vec<T> {
+    for entry in entries {
+        let id = entry.id();
+
+        match inherited.iter_mut().find(|existing| existing.id() == id) {
+            Some(existing) => *existing = entry,
+            None => inherited.push(entry),
+        }
+    }
+
+    inherited
+}
+
+fn inherit_supports(supports: Vec<Supports>, mut inherited: Vec<Supports>) -> Vec<Supports> {
+    for support in supports {
+        match inherited
+            .iter_mut()
+            .find(|existing| existing.game == support.game)
+        {
+            Some(existing) => *existing = support,
+            None => inherited.push(support),
+        }
+    }
+
+    inherited
+}
+
 #[cfg(test)]
 mod tests {
     use expect_test::expect_file;
@@ -182,4 +540,163 @@ mod tests {
     fn singular_packages_name() {
         check("singular_package.me3");
     }
+
+    #[test]
+    fn v2_game_sections() {
+        check("v2_game_sections.me3");
+    }
+
+    #[test]
+    fn v2_for_game_merges_section() {
+        let test_case =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("test-data/v2_game_sections.me3");
+        let profile = ModProfile::from_file(&test_case).expect("parse failure");
+
+        let eldenring = profile.for_game(Game::EldenRing);
+        let package_ids = eldenring
+            .packages()
+            .iter()
+            .map(|package| package.id())
+            .collect::<Vec<_>>();
+
+        assert_eq!(package_ids, ["shared", "er-only"]);
+        assert_eq!(eldenring.savefile().as_deref(), Some("modded.sl2"));
+        assert_eq!(eldenring.start_online(), Some(false));
+
+        let nightreign = profile.for_game(Game::Nightreign);
+
+        assert_eq!(nightreign.packages().len(), 1);
+        assert_eq!(nightreign.savefile().as_deref(), Some("modded-nr.sl2"));
+    }
+
+    #[test]
+    fn upgrade_v1_is_lossless() {
+        let test_case =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("test-data/basic_config.me3.toml");
+        let v1 = ModProfile::from_file(&test_case).expect("parse failure");
+        let v2 = v1.clone().upgrade();
+
+        assert_eq!(v2.version(), "v2");
+        assert_eq!(format!("{:?}", v1.natives()), format!("{:?}", v2.natives()));
+        assert_eq!(
+            format!("{:?}", v1.packages()),
+            format!("{:?}", v2.packages())
+        );
+        assert_eq!(v1.savefile(), v2.savefile());
+        assert_eq!(v1.start_online(), v2.start_online());
+        assert_eq!(v1.disable_arxan(), v2.disable_arxan());
+        assert_eq!(v1.patch_mem(), v2.patch_mem());
+    }
+
+    #[test]
+    fn upgrade_leaves_out_empty_fields() {
+        let test_case =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("test-data/basic_config.me3.toml");
+        let v2 = ModProfile::from_file(&test_case)
+            .expect("parse failure")
+            .upgrade();
+
+        let contents = toml::to_string_pretty(&v2).unwrap();
+
+        assert!(!contents.contains("metadata"), "{contents}");
+        assert!(!contents.contains("[]"), "{contents}");
+    }
+
+    #[test]
+    fn inherit_overrides_entries_by_id() {
+        let base: ModProfile = toml::from_str(
+            r#"
+profileVersion = "v1"
+savefile = "base.sl2"
+start_online = false
+
+[[supports]]
+game = "eldenring"
+
+[[packages]]
+id = "shared"
+path = "base-shared"
+
+[[packages]]
+id = "base-only"
+path = "base-only"
+"#,
+        )
+        .expect("parse failure");
+
+        let child: ModProfile = toml::from_str(
+            r#"
+profileVersion = "v1"
+extends = ["base.me3"]
+savefile = "child.sl2"
+
+[[packages]]
+id = "child-only"
+path = "child-only"
+
+[[packages]]
+id = "shared"
+path = "child-shared"
+"#,
+        )
+        .expect("parse failure");
+
+        let merged = child.inherit(base);
+        let packages = merged
+            .packages()
+            .iter()
+            .map(|package| (package.id(), package.source().display().to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(merged.version(), "v1");
+        assert_eq!(
+            packages,
+            [
+                ("shared".to_string(), "child-shared".to_string()),
+                ("base-only".to_string(), "base-only".to_string()),
+                ("child-only".to_string(), "child-only".to_string()),
+            ]
+        );
+        assert_eq!(merged.savefile().as_deref(), Some("child.sl2"));
+        assert_eq!(merged.start_online(), Some(false));
+        assert_eq!(merged.supports().len(), 1);
+    }
+
+    #[test]
+    fn inherit_v1_into_v2_merges_game_sections() {
+        let base: ModProfile = toml::from_str(
+            r#"
+profileVersion = "v1"
+
+[[packages]]
+id = "shared"
+path = "shared"
+"#,
+        )
+        .expect("parse failure");
+
+        let child: ModProfile = toml::from_str(
+            r#"
+profileVersion = "v2"
+
+[games.eldenring]
+savefile = "child.sl2"
+
+[[games.eldenring.packages]]
+id = "er-only"
+path = "er-only"
+"#,
+        )
+        .expect("parse failure");
+
+        let merged = child.inherit(base).for_game(Game::EldenRing);
+        let package_ids = merged
+            .packages()
+            .iter()
+            .map(|package| package.id())
+            .collect::<Vec<_>>();
+
+        assert_eq!(package_ids, ["shared", "er-only"]);
+        assert_eq!(merged.savefile().as_deref(), Some("child.sl2"));
+    }
 }
@@ -0,0 +1,335 @@
+//! Ordering natives and packages together, so that either kind can refer to the other in
+//! `load_before`, `load_after`, `requires` and `conflicts_with`.
+//!
+//! Natives and packages share one namespace of IDs. Each kind keeps being loaded in its own
+//! phase: every package is registered before the first native is loaded. Ordering relations
+//! between the two kinds still constrain the relative order of entries within each kind, e.g. a
+//! package ordered before a native that is itself ordered before another package.
+//!
+//! Natives with a glob pattern or directory as their path are expanded into a native for each DLL
+//! they match, and references to the ID of such an entry refer to every native it was expanded
+//! into.
+
+use std::collections::HashMap;
+
+use semver::Version;
+
+use crate::{
+    dependency::{sort_dependencies, Dependency, DependencyError, Dependent, Relation},
+    native::Native,
+    package::Package,
+};
+
+/// A native or package in a combined load order.
+#[derive(Clone, Debug)]
+pub enum LoadOrderEntry {
+    Native(Native),
+    Package(Package),
+}
+
+impl LoadOrderEntry {
+    /// The kind of this entry, for display purposes.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LoadOrderEntry::Native(_) => "native",
+            LoadOrderEntry::Package(_) => "package",
+        }
+    }
+}
+
+impl Dependency for LoadOrderEntry {
+    type UniqueId = String;
+
+    fn id(&self) -> Self::UniqueId {
+        match self {
+            LoadOrderEntry::Native(native) => native.id(),
+            LoadOrderEntry::Package(package) => package.id(),
+        }
+    }
+
+    fn version(&self) -> Option<&Version> {
+        match self {
+            LoadOrderEntry::Native(native) => native.version(),
+            LoadOrderEntry::Package(package) => package.version(),
+        }
+    }
+
+    fn loads_after(&self) -> &[Dependent<Self::UniqueId>] {
+        match self {
+            LoadOrderEntry::Native(native) => native.loads_after(),
+            LoadOrderEntry::Package(package) => package.loads_after(),
+        }
+    }
+
+    fn loads_before(&self) -> &[Dependent<Self::UniqueId>] {
+        match self {
+            LoadOrderEntry::Native(native) => native.loads_before(),
+            LoadOrderEntry::Package(package) => package.loads_before(),
+        }
+    }
+
+    fn requires(&self) -> &[Relation<Self::UniqueId>] {
+        match self {
+            LoadOrderEntry::Native(native) => native.requires(),
+            LoadOrderEntry::Package(package) => package.requires(),
+        }
+    }
+
+    fn conflicts_with(&self) -> &[Relation<Self::UniqueId>] {
+        match self {
+            LoadOrderEntry::Native(native) => native.conflicts_with(),
+            LoadOrderEntry::Package(package) => package.conflicts_with(),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        match self {
+            LoadOrderEntry::Native(native) => native.is_enabled(),
+            LoadOrderEntry::Package(package) => package.is_enabled(),
+        }
+    }
+}
+
+/// Sorts natives and packages in a single pass, returning the load order of each kind.
+pub fn sort_load_order(
+    mut natives: Vec<Native>,
+    mut packages: Vec<Package>,
+) -> Result<(Vec<Native>, Vec<Package>), DependencyError<LoadOrderEntry>> {
+    let mut groups = HashMap::<String, Vec<String>>::new();
+
+    for native in &natives {
+        if let Some(group) = native.group() {
+            groups
+                .entry(group.to_owned())
+                .or_default()
+                .push(native.id());
+        }
+    }
+
+    if !groups.is_empty() {
+        for native in &mut natives {
+            expand_groups(
+                &mut native.load_after,
+                &groups,
+                Dependent::id,
+                Dependent::to,
+            );
+            expand_groups(
+                &mut native.load_before,
+                &groups,
+                Dependent::id,
+                Dependent::to,
+            );
+            expand_groups(&mut native.requires, &groups, Relation::id, Relation::to);
+            expand_groups(
+                &mut native.conflicts_with,
+                &groups,
+                Relation::id,
+                Relation::to,
+            );
+        }
+
+        for package in &mut packages {
+            expand_groups(
+                &mut package.load_after,
+                &groups,
+                Dependent::id,
+                Dependent::to,
+            );
+            expand_groups(
+                &mut package.load_before,
+                &groups,
+                Dependent::id,
+                Dependent::to,
+            );
+            expand_groups(&mut package.requires, &groups, Relation::id, Relation::to);
+            expand_groups(
+                &mut package.conflicts_with,
+                &groups,
+                Relation::id,
+                Relation::to,
+            );
+        }
+    }
+
+    let entries = natives
+        .into_iter()
+        .map(LoadOrderEntry::Native)
+        .chain(packages.into_iter().map(LoadOrderEntry::Package))
+        .collect();
+
+    let mut natives = vec![];
+    let mut packages = vec![];
+
+    for entry in sort_dependencies(entries)? {
+        match entry {
+            LoadOrderEntry::Native(native) => natives.push(native),
+            LoadOrderEntry::Package(package) => packages.push(package),
+        }
+    }
+
+    Ok((natives, packages))
+}
+
+/// Replaces each reference to a group in `references` with a reference to every member of it.
+fn expand_groups<R: Clone>(
+    references: &mut Vec<R>,
+    groups: &HashMap<String, Vec<String>>,
+    id: fn(&R) -> String,
+    to: fn(&R, String) -> R,
+) {
+    if !references
+        .iter()
+        .any(|reference| groups.contains_key(&id(reference)))
+    {
+        return;
+    }
+
+    *references = references
+        .iter()
+        .flat_map(|reference| match groups.get(&id(reference)) {
+            Some(members) => members
+                .iter()
+                .map(|member| to(reference, member.clone()))
+                .collect(),
+            None => vec![reference.clone()],
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::sort_load_order;
+    use crate::{dependency::Dependency, native::Native, package::Package};
+
+    #[derive(Deserialize)]
+    struct Entries {
+        #[serde(default)]
+        natives: Vec<Native>,
+        #[serde(default)]
+        packages: Vec<Package>,
+    }
+
+    fn sort(source: &str) -> Result<(Vec<String>, Vec<String>), String> {
+        let Entries { natives, packages } = toml::from_str(source).unwrap();
+
+        sort_load_order(natives, packages)
+            .map(|(natives, packages)| {
+                (
+                    natives.iter().map(Dependency::id).collect(),
+                    packages.iter().map(Dependency::id).collect(),
+                )
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn orders_across_kinds() {
+        let (natives, packages) = sort(
+            r#"
+[[natives]]
+path = "framework.dll"
+
+[[natives]]
+path = "addon.dll"
+requires = [{ id = "addon-assets" }]
+
+[[packages]]
+id = "overrides"
+path = "overrides"
+
+[[packages]]
+id = "addon-assets"
+path = "addon"
+load_before = [{ id = "framework.dll", optional = false }]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(natives, ["framework.dll", "addon.dll"]);
+        assert_eq!(packages, ["addon-assets", "overrides"]);
+    }
+
+    #[test]
+    fn reports_cross_kind_problems() {
+        let error = sort(
+            r#"
+[[natives]]
+id = "shared"
+path = "shared.dll"
+
+[[packages]]
+id = "shared"
+path = "shared"
+"#,
+        )
+        .unwrap_err();
+
+        assert_eq!(error, "shared is used as the ID of more than one entry");
+
+        let error = sort(
+            r#"
+[[natives]]
+path = "mod.dll"
+load_after = [{ id = "assets", optional = false }]
+
+[[packages]]
+id = "assets"
+path = "assets"
+load_after = [{ id = "mod.dll", optional = false }]
+"#,
+        )
+        .unwrap_err();
+
+        assert!(
+            error.starts_with("Dependencies form a cycle: "),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn refers_to_expanded_natives_by_group() {
+        let Entries { natives, packages } = toml::from_str(
+            r#"
+[[natives]]
+path = "framework.dll"
+
+[[natives]]
+id = "plugins"
+path = "plugins/**/*.dll"
+load_after = [{ id = "framework.dll", optional = false }]
+
+[[natives]]
+path = "addon.dll"
+load_before = [{ id = "plugins", optional = false }]
+requires = [{ id = "plugins" }]
+"#,
+        )
+        .unwrap();
+
+        let natives = natives
+            .into_iter()
+            .flat_map(|native| match native.id().as_str() {
+                "plugins" => vec![
+                    native.for_match("plugins/a/hook.dll".into(), "a/hook.dll"),
+                    native.for_match("plugins/b/hook.dll".into(), "b/hook.dll"),
+                ],
+                _ => vec![native],
+            })
+            .collect();
+
+        let (natives, _) = sort_load_order(natives, packages).unwrap();
+
+        assert_eq!(
+            natives.iter().map(Dependency::id).collect::<Vec<_>>(),
+            [
+                "framework.dll",
+                "addon.dll",
+                "plugins/a/hook.dll",
+                "plugins/b/hook.dll"
+            ]
+        );
+    }
+}
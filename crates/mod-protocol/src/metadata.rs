@@ -0,0 +1,49 @@
+use schemars::JsonSchema;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+/// Descriptive information about a native or package. Apart from the version, which can be
+/// constrained by dependents, it does not affect how the entry is loaded.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct Metadata {
+    /// A human readable name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// The version of this entry, following semantic versioning (e.g. `1.2.0`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<Version>,
+
+    /// The people or teams that made this entry.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+
+    /// A link to the page this entry is distributed on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+
+    /// A short description of what this entry does.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fills in the fields that aren't set with the ones from `other`, e.g. the metadata shipped
+    /// with a package.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            name: self.name.or(other.name),
+            version: self.version.or(other.version),
+            authors: match self.authors.is_empty() {
+                true => other.authors,
+                false => self.authors,
+            },
+            homepage: self.homepage.or(other.homepage),
+            description: self.description.or(other.description),
+        }
+    }
+}
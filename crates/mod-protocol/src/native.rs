@@ -1,10 +1,12 @@
 use std::path::PathBuf;
 
 use schemars::JsonSchema;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    dependency::{Dependency, Dependent},
+    dependency::{Dependency, Dependent, Relation},
+    metadata::Metadata,
     package::{ModFile, WithPackageSource},
 };
 
@@ -26,7 +28,17 @@ pub enum NativeInitializerCondition {
 
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct Native {
+    /// The unique identifier for this native, shared with the IDs of packages. Defaults to the
+    /// file name of the DLL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<String>,
+
     /// Path to the DLL. Can be relative to the mod profile.
+    ///
+    /// A glob pattern like `plugins/*.dll`, or a directory, loads every DLL it matches with the
+    /// settings of this entry. The matched DLLs are loaded in order of their paths, and are
+    /// identified by this entry's ID followed by their path relative to the pattern, e.g.
+    /// `plugins/nested/a.dll`. Relations to this entry's ID apply to every matched DLL.
     pub path: ModFile,
 
     /// If this native fails to load and this value is false, treat it as a critical error.
@@ -37,31 +49,73 @@ pub struct Native {
     #[serde(default = "on")]
     pub enabled: bool,
 
-    #[serde(default)]
-    load_before: Vec<Dependent<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) load_before: Vec<Dependent<String>>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) load_after: Vec<Dependent<String>>,
+
+    /// Natives or packages that must be loaded for this native to work, in any order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) requires: Vec<Relation<String>>,
 
-    #[serde(default)]
-    load_after: Vec<Dependent<String>>,
+    /// Natives or packages that can't be loaded together with this native.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) conflicts_with: Vec<Relation<String>>,
 
     /// An optional symbol to be called after this native successfully loads.
     pub initializer: Option<NativeInitializerCondition>,
 
     /// An optional symbol to be called when this native successfully is queued for unload.
     pub finalizer: Option<String>,
+
+    /// Descriptive information about this native.
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+
+    /// ID of the entry with a glob pattern or directory as its path that this native was matched
+    /// by, if any.
+    #[serde(skip)]
+    pub(crate) group: Option<String>,
 }
 
 impl Native {
     pub fn new<P: Into<PathBuf>>(path: P) -> Self {
         Self {
+            id: None,
             path: ModFile(path.into()),
             optional: false,
             enabled: true,
             load_after: vec![],
             load_before: vec![],
+            requires: vec![],
+            conflicts_with: vec![],
             initializer: None,
             finalizer: None,
+            metadata: Metadata::default(),
+            group: None,
         }
     }
+
+    /// Creates an entry for one of the DLLs matched by the glob pattern or directory this native
+    /// points to, with the same settings. It is identified by this native's ID followed by
+    /// `relative`, the path of the DLL relative to the pattern, and belongs to this native's group.
+    pub fn for_match(&self, path: PathBuf, relative: &str) -> Self {
+        let group = self.id();
+
+        Self {
+            id: Some(format!("{group}/{relative}")),
+            path: ModFile(path),
+            group: Some(group),
+            ..self.clone()
+        }
+    }
+
+    /// ID of the entry with a glob pattern or directory as its path that this native was matched
+    /// by, which refers to every native matched by that entry.
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
 }
 
 impl WithPackageSource for Native {
@@ -72,17 +126,28 @@ impl WithPackageSource for Native {
     fn source_mut(&mut self) -> &mut crate::package::ModFile {
         &mut self.path
     }
+
+    fn allows_pattern(&self) -> bool {
+        true
+    }
 }
 
 impl Dependency for Native {
     type UniqueId = String;
 
     fn id(&self) -> Self::UniqueId {
-        self.path
-            .0
-            .file_name()
-            .map(|f| f.to_string_lossy().to_string())
-            .expect("native had no file name")
+        self.id.clone().unwrap_or_else(|| {
+            let path = &self.path.0;
+
+            path.file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+
+    fn version(&self) -> Option<&Version> {
+        self.metadata.version.as_ref()
     }
 
     fn loads_after(&self) -> &[Dependent<Self::UniqueId>] {
@@ -92,4 +157,16 @@ impl Dependency for Native {
     fn loads_before(&self) -> &[Dependent<Self::UniqueId>] {
         &self.load_before
     }
+
+    fn requires(&self) -> &[Relation<Self::UniqueId>] {
+        &self.requires
+    }
+
+    fn conflicts_with(&self) -> &[Relation<Self::UniqueId>] {
+        &self.conflicts_with
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
 }
@@ -1,21 +1,32 @@
 use std::{
+    fs, io, mem,
     ops::Deref,
     path::{Path, PathBuf},
 };
 
 use schemars::JsonSchema;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 
-use crate::dependency::{Dependency, Dependent};
+use crate::{
+    dependency::{Dependency, Dependent, Relation},
+    metadata::Metadata,
+    variables::{ExpandError, PathVariables, PROFILE_DIR},
+};
 
 pub trait WithPackageSource {
     fn source(&self) -> &ModFile;
 
     fn source_mut(&mut self) -> &mut ModFile;
+
+    /// Whether the source may be a glob pattern that matches several files.
+    fn allows_pattern(&self) -> bool {
+        false
+    }
 }
 
 /// A filesystem path to the contents of a package. May be relative to the [ModProfile] containing
-/// it.
+/// it, and may contain variables like `${GAME_DIR}` or start with `~`.
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct ModFile(pub(crate) PathBuf);
 
@@ -33,17 +44,69 @@ impl ModFile {
         self.0.is_relative()
     }
 
+    /// Resolves a path relative to, or starting with `${PROFILE_DIR}`, against `base`. Paths that
+    /// start with other variables are left for [`ModFile::expand`].
     pub fn make_absolute(&mut self, base: &Path) {
-        if self.0.is_relative() {
+        let profile_dir = format!("${{{PROFILE_DIR}}}");
+
+        if let Ok(rest) = self.0.strip_prefix(&profile_dir) {
+            self.0 = base.join(rest);
+        } else if self.0.is_relative() && !self.starts_with_variable() {
             self.0 = base.join(&self.0);
         }
     }
+
+    /// Whether the path is a glob pattern, e.g. `plugins/*.dll`, rather than the path of an
+    /// existing file or directory.
+    pub fn is_pattern(&self) -> bool {
+        self.0
+            .to_str()
+            .is_some_and(|path| path.contains(['*', '?', '[', '{']))
+            && !self.0.exists()
+    }
+
+    /// Whether the path contains variables that still need to be expanded.
+    pub fn has_variables(&self) -> bool {
+        PathVariables::contains_variables(&self.0)
+    }
+
+    /// Replaces the variables in the path with their values.
+    pub fn expand(&mut self, variables: &PathVariables) -> Result<(), ExpandError> {
+        if self.has_variables() {
+            self.0 = variables.expand(&self.0)?;
+        }
+
+        Ok(())
+    }
+
+    fn starts_with_variable(&self) -> bool {
+        self.0.to_str().is_some_and(|path| path.starts_with("${")) || self.0.starts_with("~")
+    }
 }
 
 fn on() -> bool {
     true
 }
 
+/// Name of the file in a package directory that describes the package with the same fields as
+/// [`Metadata`].
+pub const PACKAGE_MANIFEST: &str = "me3-package.toml";
+
+/// Glob patterns for files that are never mapped from a package unless an `include` pattern
+/// matches them, like documentation, version control data and editor leftovers.
+pub const DEFAULT_EXCLUDE: &[&str] = &[
+    "**/.*",
+    "**/.*/**",
+    "**/readme*",
+    "**/*.md",
+    "**/*.psd",
+    "**/*.bak",
+    "**/*.tmp",
+    "**/thumbs.db",
+    "**/desktop.ini",
+    PACKAGE_MANIFEST,
+];
+
 /// A package is a source for files that override files within the existing games DVDBND archives.
 /// It points to a local path containing assets matching the hierarchy they would be served under in
 /// the DVDBND.
@@ -56,17 +119,64 @@ pub struct Package {
     #[serde(default = "on")]
     pub enabled: bool,
 
-    /// A path to the source of this package.
+    /// A path to the source of this package, either a directory or a `.zip` archive.
     #[serde(alias = "source")]
     pub(crate) path: ModFile,
 
-    /// A list of package IDs that this package should load after.
-    #[serde(default)]
+    /// A list of native or package IDs that this package should load after.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub(crate) load_after: Vec<Dependent<String>>,
 
-    /// A list of packages that this package should load before.
-    #[serde(default)]
+    /// A list of natives or packages that this package should load before.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub(crate) load_before: Vec<Dependent<String>>,
+
+    /// Natives or packages that must be loaded for this package to work, in any order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) requires: Vec<Relation<String>>,
+
+    /// Natives or packages that can't be loaded together with this package.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) conflicts_with: Vec<Relation<String>>,
+
+    /// VFS mount roots (e.g. `data1` or `dlc02`) that this package's files apply to. Files apply
+    /// to every root if this is empty.
+    ///
+    /// Files in a top-level `@<root>` directory of the package only apply to that root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) roots: Vec<String>,
+
+    /// Serve the source, which must be a single file, as this asset path instead of scanning it as
+    /// a directory. Set for packages created from `files` entries.
+    #[serde(default)]
+    pub(crate) target: Option<String>,
+
+    /// Glob patterns, relative to the package source, of the files that are mapped. Every file is
+    /// mapped if this is empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) include: Vec<String>,
+
+    /// Glob patterns, relative to the package source, of files that are not mapped. Documentation,
+    /// version control data and backup files are excluded by default.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) exclude: Vec<String>,
+
+    /// An asset path that the package's files are mapped under, e.g. `chr` to map
+    /// `c0000.anibnd.dcx` as `chr/c0000.anibnd.dcx`. If it starts with a mount root (e.g.
+    /// `dlc02:/chr`) the files only apply to that root.
+    #[serde(default)]
+    pub(crate) mount_point: Option<String>,
+
+    /// Asset paths, e.g. `data0:/sfx/sfxbnd_c0000.ffxbnd.dcx`, that are reported as absent
+    /// instead of being loaded from the game archives. Files in the package named after an asset
+    /// with a `.me3-hide` suffix hide that asset too.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) hide: Vec<String>,
+
+    /// Descriptive information about this package. Fields that aren't set are read from the
+    /// `me3-package.toml` file in the package directory, if there is one.
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
 }
 
 impl Package {
@@ -77,6 +187,15 @@ impl Package {
             enabled: true,
             load_after: vec![],
             load_before: vec![],
+            requires: vec![],
+            conflicts_with: vec![],
+            roots: vec![],
+            target: None,
+            include: vec![],
+            exclude: vec![],
+            mount_point: None,
+            hide: vec![],
+            metadata: Metadata::default(),
         }
     }
 
@@ -85,6 +204,28 @@ impl Package {
     pub fn make_absolute(&mut self, base: &Path) {
         self.path = ModFile(base.join(&self.path.0));
     }
+
+    /// Fills in metadata that isn't set by the profile from the package's `me3-package.toml`, if
+    /// it has one.
+    pub fn load_manifest(&mut self) -> Result<(), io::Error> {
+        let contents = match fs::read_to_string(self.path.join(PACKAGE_MANIFEST)) {
+            Ok(contents) => contents,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::NotADirectory
+                ) =>
+            {
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+
+        let manifest = toml::from_str::<Metadata>(&contents).map_err(io::Error::other)?;
+        self.metadata = mem::take(&mut self.metadata).or(manifest);
+
+        Ok(())
+    }
 }
 
 impl WithPackageSource for Package {
@@ -106,6 +247,10 @@ impl Dependency for Package {
             .unwrap_or_else(|| self.path.to_string_lossy().into())
     }
 
+    fn version(&self) -> Option<&Version> {
+        self.metadata.version.as_ref()
+    }
+
     fn loads_after(&self) -> &[crate::dependency::Dependent<Self::UniqueId>] {
         &self.load_after
     }
@@ -113,14 +258,114 @@ impl Dependency for Package {
     fn loads_before(&self) -> &[crate::dependency::Dependent<Self::UniqueId>] {
         &self.load_before
     }
+
+    fn requires(&self) -> &[Relation<Self::UniqueId>] {
+        &self.requires
+    }
+
+    fn conflicts_with(&self) -> &[Relation<Self::UniqueId>] {
+        &self.conflicts_with
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
 }
 
 pub trait AssetOverrideSource {
     fn asset_path(&self) -> &Path;
+
+    /// Identifier of the package the assets come from, used to trace which package an asset
+    /// override was served from.
+    fn source_id(&self) -> String;
+
+    /// VFS mount roots the assets apply to, or an empty slice if they apply to every root.
+    fn asset_roots(&self) -> &[String] {
+        &[]
+    }
+
+    /// Asset path that [`Self::asset_path`] is served as if it is a single file rather than a
+    /// directory of assets.
+    fn asset_target(&self) -> Option<&str> {
+        None
+    }
+
+    /// Glob patterns of the assets to map, or an empty slice to map every asset.
+    fn asset_include(&self) -> &[String] {
+        &[]
+    }
+
+    /// Glob patterns of the assets to skip in addition to [`DEFAULT_EXCLUDE`].
+    fn asset_exclude(&self) -> &[String] {
+        &[]
+    }
+
+    /// Asset path prefix the assets are mapped under.
+    fn asset_mount_point(&self) -> Option<&str> {
+        None
+    }
+
+    /// Asset paths that are hidden instead of overridden.
+    fn asset_hide(&self) -> &[String] {
+        &[]
+    }
 }
 
 impl AssetOverrideSource for &Package {
     fn asset_path(&self) -> &Path {
         self.path.0.as_path()
     }
+
+    fn source_id(&self) -> String {
+        self.id()
+    }
+
+    fn asset_roots(&self) -> &[String] {
+        &self.roots
+    }
+
+    fn asset_target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    fn asset_include(&self) -> &[String] {
+        &self.include
+    }
+
+    fn asset_exclude(&self) -> &[String] {
+        &self.exclude
+    }
+
+    fn asset_mount_point(&self) -> Option<&str> {
+        self.mount_point.as_deref()
+    }
+
+    fn asset_hide(&self) -> &[String] {
+        &self.hide
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::Package;
+
+    #[test]
+    fn manifest_fills_in_metadata() {
+        let test_data = Path::new(env!("CARGO_MANIFEST_DIR")).join("test-data");
+
+        let mut package = Package::new(test_data.join("versioned-package"));
+        package.metadata.name = Some("Renamed".to_owned());
+        package.load_manifest().unwrap();
+
+        assert_eq!(package.metadata.name.as_deref(), Some("Renamed"));
+        assert_eq!(package.metadata.version, Some("1.2.0".parse().unwrap()));
+        assert_eq!(package.metadata.authors, ["me3"]);
+
+        let mut package = Package::new(test_data.join("basic_config.me3.toml"));
+        package.load_manifest().unwrap();
+
+        assert!(package.metadata.is_empty());
+    }
 }
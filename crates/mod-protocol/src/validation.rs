@@ -0,0 +1,1046 @@
+//! Checks for mistakes in mod profiles that would otherwise only show up when the profile is
+//! launched, reported with the location in the profile source they were found at.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    fmt, iter,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use schemars::schema_for;
+use serde_json::{value::RawValue, Value};
+
+use crate::{
+    dependency::{Dependency, DependencyError},
+    file::FileMapping,
+    inherit_entries,
+    load_order::sort_load_order,
+    native::Native,
+    package::{Package, WithPackageSource},
+    Game, ModProfile,
+};
+
+/// Serde aliases that are accepted for fields but are not part of the generated schema.
+const ALIASES: &[(&str, &str)] = &[
+    ("native", "natives"),
+    ("package", "packages"),
+    ("source", "path"),
+    ("path", "source"),
+];
+
+/// Characters that can't be used in savefile names.
+const RESERVED_SAVEFILE_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// A single problem found in a mod profile.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// Byte range of the profile source this diagnostic refers to, if it can be attributed to
+    /// a specific part of the profile.
+    pub span: Option<Range<usize>>,
+}
+
+impl Diagnostic {
+    fn error(message: impl Into<String>, span: Option<Range<usize>>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            span,
+        }
+    }
+
+    fn warning(message: impl Into<String>, span: Option<Range<usize>>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+/// A 1-based line and column in a profile source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The result of validating a mod profile.
+#[derive(Debug)]
+pub struct Validation {
+    path: PathBuf,
+    source: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validation {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// Get the line and column of a byte offset into the profile source.
+    pub fn location(&self, offset: usize) -> Location {
+        let offset = offset.min(self.source.len());
+        let before = &self.source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+        Location {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    /// Get the text of a 1-based line in the profile source.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        self.source.lines().nth(line.checked_sub(1)?)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Toml,
+    Json,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Result<Self, std::io::Error> {
+        match path.extension().and_then(|path| path.to_str()) {
+            Some("toml") | Some("me3") | None => Ok(Format::Toml),
+            Some("json") => Ok(Format::Json),
+            Some(format) => Err(std::io::Error::other(format!("{format} is unsupported"))),
+        }
+    }
+}
+
+/// Validate the mod profile at `path`.
+///
+/// `inherited` is the profile this profile extends, if any. Entries from it can be referenced
+/// by `load_before` and `load_after` and overridden without being reported as duplicates.
+pub fn validate_file(
+    path: &Path,
+    inherited: Option<&ModProfile>,
+) -> Result<Validation, std::io::Error> {
+    let format = Format::from_path(path)?;
+    let source = std::fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+
+    let diagnostics = validate_source(&source, format, base_dir, inherited);
+
+    Ok(Validation {
+        path: path.to_path_buf(),
+        source,
+        diagnostics,
+    })
+}
+
+fn validate_source(
+    source: &str,
+    format: Format,
+    base_dir: &Path,
+    inherited: Option<&ModProfile>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    let document = match format {
+        Format::Toml => toml::de::DeTable::parse(source)
+            .map(|table| Node::from_toml_table(table.span(), table.get_ref()))
+            .map_err(|e| toml_diagnostic(&e)),
+        Format::Json => serde_json::from_str::<&RawValue>(source)
+            .map_err(|e| json_diagnostic(source, &e))
+            .and_then(|raw| Node::from_json(source, raw).map_err(|e| json_diagnostic(source, &e))),
+    };
+
+    let document = match document {
+        Ok(document) => document,
+        Err(diagnostic) => return vec![diagnostic],
+    };
+
+    let schema = schema_for!(ModProfile);
+    let schema = schema.as_value();
+    check_keys(&document, schema, schema, &mut diagnostics);
+
+    let profile = match format {
+        Format::Toml => toml::from_str::<ModProfile>(source).map_err(|e| toml_diagnostic(&e)),
+        Format::Json => {
+            serde_json::from_str::<ModProfile>(source).map_err(|e| json_diagnostic(source, &e))
+        }
+    };
+
+    let profile = match profile {
+        Ok(profile) => profile,
+        Err(diagnostic) => {
+            diagnostics.push(diagnostic);
+            return diagnostics;
+        }
+    };
+
+    check_savefile(
+        profile.savefile(),
+        document.get("savefile"),
+        &mut diagnostics,
+    );
+
+    let inherited_natives = inherited.map(ModProfile::natives).unwrap_or_default();
+    let inherited_packages = inherited
+        .map(|inherited| {
+            let files = inherited.files().into_iter().map(Package::from);
+            inherited
+                .packages()
+                .into_iter()
+                .chain(files)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let shared_natives = entries(&document, &["natives", "native"], profile.natives());
+    let shared_packages = package_entries(&document, profile.packages(), profile.files());
+
+    let mut checker = EntryChecker {
+        base_dir,
+        diagnostics: &mut diagnostics,
+    };
+
+    checker.check_all(
+        (&inherited_natives, &inherited_packages),
+        (&[], &[]),
+        (&shared_natives, &shared_packages),
+    );
+
+    if let ModProfile::V2(v2) = &profile {
+        for (game, section) in &v2.games {
+            let Some(node) = document.get("games").and_then(|games| {
+                games.find(|key| Game::try_from(key.to_owned()).ok() == Some(*game))
+            }) else {
+                continue;
+            };
+
+            let natives = entries(node, &["natives", "native"], section.natives.clone());
+            let packages = package_entries(node, section.packages.clone(), section.files.clone());
+
+            checker.check_all(
+                (&inherited_natives, &inherited_packages),
+                (&shared_natives, &shared_packages),
+                (&natives, &packages),
+            );
+
+            check_savefile(
+                section.savefile.clone(),
+                node.get("savefile"),
+                checker.diagnostics,
+            );
+        }
+    }
+
+    diagnostics
+}
+
+/// A value in a profile document along with the location it was defined at.
+#[derive(Debug)]
+struct Node {
+    span: Range<usize>,
+    kind: NodeKind,
+}
+
+#[derive(Debug)]
+enum NodeKind {
+    Table(Vec<(String, Range<usize>, Node)>),
+    Array(Vec<Node>),
+    String(String),
+    Other,
+}
+
+impl Node {
+    fn from_toml_table(span: Range<usize>, table: &toml::de::DeTable<'_>) -> Self {
+        let entries = table
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.get_ref().to_string(),
+                    key.span(),
+                    Node::from_toml(value.span(), value.get_ref()),
+                )
+            })
+            .collect();
+
+        Node {
+            span,
+            kind: NodeKind::Table(entries),
+        }
+    }
+
+    fn from_toml(span: Range<usize>, value: &toml::de::DeValue<'_>) -> Self {
+        use toml::de::DeValue;
+
+        let kind = match value {
+            DeValue::Table(table) => return Node::from_toml_table(span, table),
+            DeValue::Array(array) => NodeKind::Array(
+                array
+                    .iter()
+                    .map(|value| Node::from_toml(value.span(), value.get_ref()))
+                    .collect(),
+            ),
+            DeValue::String(string) => NodeKind::String(string.to_string()),
+            _ => NodeKind::Other,
+        };
+
+        Node { span, kind }
+    }
+
+    fn from_json(source: &str, raw: &RawValue) -> Result<Self, serde_json::Error> {
+        let text = raw.get();
+        let start = text.as_ptr() as usize - source.as_ptr() as usize;
+        let span = start..start + text.len();
+
+        let kind = match text.as_bytes().first() {
+            Some(b'{') => {
+                let table = serde_json::from_str::<BTreeMap<Cow<'_, str>, &RawValue>>(text)?;
+                let mut entries = table
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let node = Node::from_json(source, value)?;
+                        let quoted = format!("\"{key}\"");
+                        let key_span = source[..node.span.start]
+                            .rfind(&quoted)
+                            .map_or(node.span.clone(), |index| index..index + quoted.len());
+
+                        Ok((key.into_owned(), key_span, node))
+                    })
+                    .collect::<Result<Vec<_>, serde_json::Error>>()?;
+
+                entries.sort_by_key(|(_, span, _)| span.start);
+
+                NodeKind::Table(entries)
+            }
+            Some(b'[') => NodeKind::Array(
+                serde_json::from_str::<Vec<&RawValue>>(text)?
+                    .into_iter()
+                    .map(|value| Node::from_json(source, value))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Some(b'"') => NodeKind::String(serde_json::from_str(text)?),
+            _ => NodeKind::Other,
+        };
+
+        Ok(Node { span, kind })
+    }
+
+    fn find(&self, mut predicate: impl FnMut(&str) -> bool) -> Option<&Node> {
+        match &self.kind {
+            NodeKind::Table(entries) => entries
+                .iter()
+                .find(|(key, _, _)| predicate(key))
+                .map(|(_, _, node)| node),
+            _ => None,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Node> {
+        self.find(|candidate| candidate == key)
+    }
+
+    fn index(&self, index: usize) -> Option<&Node> {
+        match &self.kind {
+            NodeKind::Array(items) => items.get(index),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+fn toml_diagnostic(error: &toml::de::Error) -> Diagnostic {
+    Diagnostic::error(error.message().trim(), error.span())
+}
+
+fn json_diagnostic(source: &str, error: &serde_json::Error) -> Diagnostic {
+    let offset = (error.line() > 0).then(|| {
+        let line_start: usize = source
+            .split_inclusive('\n')
+            .take(error.line() - 1)
+            .map(str::len)
+            .sum();
+
+        (line_start + error.column().saturating_sub(1)).min(source.len())
+    });
+
+    let message = error.to_string();
+    let message = match message.rfind(" at line ") {
+        Some(index) => &message[..index],
+        None => &message,
+    };
+
+    Diagnostic::error(message, offset.map(|offset| offset..offset))
+}
+
+/// Report keys that aren't part of the profile schema. These would be silently ignored when the
+/// profile is loaded.
+fn check_keys(node: &Node, schema: &Value, root: &Value, diagnostics: &mut Vec<Diagnostic>) {
+    let mut schemas = vec![];
+    collect_subschemas(node, schema, root, &mut schemas);
+
+    match &node.kind {
+        NodeKind::Table(entries) => {
+            let properties = schemas
+                .iter()
+                .filter_map(|schema| schema.get("properties")?.as_object())
+                .collect::<Vec<_>>();
+
+            let additional = schemas
+                .iter()
+                .find_map(|schema| schema.get("additionalProperties").filter(|v| v.is_object()));
+
+            if properties.is_empty() && additional.is_none() {
+                return;
+            }
+
+            for (key, key_span, value) in entries {
+                let aliased = ALIASES
+                    .iter()
+                    .filter(|(alias, _)| alias == key)
+                    .map(|(_, name)| *name);
+
+                let property = iter::once(key.as_str())
+                    .chain(aliased)
+                    .find_map(|name| {
+                        properties
+                            .iter()
+                            .find_map(|properties| properties.get(name))
+                    })
+                    .or(additional);
+
+                match property {
+                    Some(property) => check_keys(value, property, root, diagnostics),
+                    None => diagnostics.push(Diagnostic::error(
+                        format!("unknown key `{key}`"),
+                        Some(key_span.clone()),
+                    )),
+                }
+            }
+        }
+        NodeKind::Array(items) => {
+            let Some(item_schema) = schemas.iter().find_map(|schema| schema.get("items")) else {
+                return;
+            };
+
+            for item in items {
+                check_keys(item, item_schema, root, diagnostics);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collect `schema` and every schema it refers to or is composed of that applies to `node`.
+fn collect_subschemas<'s>(
+    node: &Node,
+    schema: &'s Value,
+    root: &'s Value,
+    out: &mut Vec<&'s Value>,
+) {
+    out.push(schema);
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        if let Some(resolved) = reference
+            .strip_prefix("#/$defs/")
+            .and_then(|name| root.get("$defs")?.get(name))
+        {
+            collect_subschemas(node, resolved, root, out);
+        }
+    }
+
+    for key in ["allOf", "anyOf", "oneOf"] {
+        let Some(branches) = schema.get(key).and_then(Value::as_array) else {
+            continue;
+        };
+
+        // Tagged variants are chosen by the constant values of their tag properties.
+        let matching = branches
+            .iter()
+            .filter(|branch| {
+                let Some(properties) = branch.get("properties").and_then(Value::as_object) else {
+                    return false;
+                };
+
+                properties
+                    .iter()
+                    .any(|(_, property)| property.get("const").is_some())
+                    && properties.iter().all(|(key, property)| {
+                        property.get("const").is_none_or(|constant| {
+                            node.get(key).and_then(Node::as_str) == constant.as_str()
+                        })
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let branches = if matching.is_empty() {
+            branches.iter().collect()
+        } else {
+            matching
+        };
+
+        for branch in branches {
+            collect_subschemas(node, branch, root, out);
+        }
+    }
+}
+
+/// A savefile name that can't be used as a file name on Windows.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum InvalidSavefileName {
+    #[error("savefile name `{0}` contains reserved character {1:?}")]
+    ReservedChar(String, char),
+
+    #[error("savefile name `{0}` can't end with a dot or space")]
+    TrailingDotOrSpace(String),
+}
+
+/// Checks that `savefile` is a valid file name on Windows, see
+/// <https://learn.microsoft.com/en-us/windows/win32/fileio/naming-a-file#naming-conventions>.
+pub fn check_savefile_name(savefile: &str) -> Result<(), InvalidSavefileName> {
+    if let Some(c) = savefile
+        .chars()
+        .find(|c| RESERVED_SAVEFILE_CHARS.contains(c) || c.is_control())
+    {
+        return Err(InvalidSavefileName::ReservedChar(savefile.to_owned(), c));
+    }
+
+    if savefile.ends_with(['.', ' ']) {
+        return Err(InvalidSavefileName::TrailingDotOrSpace(savefile.to_owned()));
+    }
+
+    Ok(())
+}
+
+fn check_savefile(
+    savefile: Option<String>,
+    node: Option<&Node>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if let Some(Err(e)) = savefile.as_deref().map(check_savefile_name) {
+        diagnostics.push(Diagnostic::error(
+            e.to_string(),
+            node.map(|node| node.span.clone()),
+        ));
+    }
+}
+
+/// A native or package paired with the document node it was parsed from.
+struct Entry<'a, T> {
+    item: T,
+    node: Option<&'a Node>,
+}
+
+fn entries<'a, T>(table: &'a Node, keys: &[&str], items: Vec<T>) -> Vec<Entry<'a, T>> {
+    let array = table.find(|key| keys.contains(&key));
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| Entry {
+            item,
+            node: array.and_then(|array| array.index(index)),
+        })
+        .collect()
+}
+
+/// Packages and file mappings share IDs and are ordered together, so they're checked as one list.
+fn package_entries(
+    table: &Node,
+    packages: Vec<Package>,
+    files: Vec<FileMapping>,
+) -> Vec<Entry<'_, Package>> {
+    let files = files.into_iter().map(Package::from).collect();
+
+    let mut packages = entries(table, &["packages", "package"], packages);
+    packages.extend(entries(table, &["files"], files));
+    packages
+}
+
+/// IDs of the entries of one kind that are loaded.
+fn ids<T: Dependency<UniqueId = String>>(
+    inherited: &[T],
+    shared: &[Entry<'_, T>],
+    own: &[Entry<'_, T>],
+) -> HashSet<String> {
+    inherited
+        .iter()
+        .map(Dependency::id)
+        .chain(shared.iter().chain(own).map(|entry| entry.item.id()))
+        .collect()
+}
+
+/// The entries of one kind that are loaded, with `own` entries replacing inherited ones.
+fn loaded_entries<T: Dependency + Clone>(
+    inherited: &[T],
+    shared: &[Entry<'_, T>],
+    own: &[Entry<'_, T>],
+) -> Vec<T> {
+    inherit_entries(
+        own.iter().map(|entry| entry.item.clone()).collect(),
+        inherit_entries(
+            shared.iter().map(|entry| entry.item.clone()).collect(),
+            inherited.to_vec(),
+        ),
+    )
+}
+
+struct EntryChecker<'a> {
+    base_dir: &'a Path,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl EntryChecker<'_> {
+    /// Check the natives and packages in `own` that are loaded after `inherited` and `shared`
+    /// entries.
+    fn check_all(
+        &mut self,
+        inherited: (&[Native], &[Package]),
+        shared: (&[Entry<'_, Native>], &[Entry<'_, Package>]),
+        own: (&[Entry<'_, Native>], &[Entry<'_, Package>]),
+    ) {
+        let native_ids = ids(inherited.0, shared.0, own.0);
+        let package_ids = ids(inherited.1, shared.1, own.1);
+
+        self.check("native", inherited.0, shared.0, own.0, &package_ids);
+        self.check("package", inherited.1, shared.1, own.1, &native_ids);
+
+        let own_nodes = || {
+            own.0
+                .iter()
+                .map(|entry| (entry.item.id(), entry.node))
+                .chain(own.1.iter().map(|entry| (entry.item.id(), entry.node)))
+        };
+
+        for id in native_ids.intersection(&package_ids) {
+            let span = own_nodes()
+                .find(|(other, _)| other == id)
+                .and_then(|(_, node)| node)
+                .map(|node| node.get("id").unwrap_or(node).span.clone());
+
+            self.diagnostics.push(Diagnostic::error(
+                format!("native id `{id}` is also used by a package"),
+                span,
+            ));
+        }
+
+        if !native_ids.is_disjoint(&package_ids) {
+            return;
+        }
+
+        let natives = loaded_entries(inherited.0, shared.0, own.0);
+        let packages = loaded_entries(inherited.1, shared.1, own.1);
+
+        let Err(error) = sort_load_order(natives, packages) else {
+            return;
+        };
+
+        let (message, ids) = match &error {
+            DependencyError::Cyclic(cycle) => (
+                format!("dependencies form a cycle: {cycle}"),
+                cycle.ids().cloned().collect(),
+            ),
+            DependencyError::IncompatibleVersion { dependent, .. }
+            | DependencyError::MissingRequirement { dependent, .. }
+            | DependencyError::Conflict { dependent, .. } => {
+                (error.to_string(), vec![dependent.clone()])
+            }
+            // Missing dependencies and duplicate IDs were reported above, and versions declared in
+            // package manifests aren't known here.
+            _ => return,
+        };
+
+        let span = own_nodes()
+            .find(|(id, _)| ids.contains(id))
+            .and_then(|(_, node)| node)
+            .map(|node| node.span.clone());
+
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+
+    /// Check the entries of one kind in `own`, references to `other_ids` refer to entries of the
+    /// other kind.
+    fn check<T>(
+        &mut self,
+        kind: &str,
+        inherited: &[T],
+        shared: &[Entry<'_, T>],
+        own: &[Entry<'_, T>],
+        other_ids: &HashSet<String>,
+    ) where
+        T: Dependency<UniqueId = String> + WithPackageSource + Clone,
+    {
+        let mut seen = shared
+            .iter()
+            .map(|entry| entry.item.id())
+            .collect::<HashSet<_>>();
+
+        for entry in own {
+            let id = entry.item.id();
+            let span = entry
+                .node
+                .map(|node| node.get("id").unwrap_or(node).span.clone());
+
+            if !seen.insert(id.clone()) {
+                self.diagnostics.push(Diagnostic::error(
+                    format!("duplicate {kind} id `{id}`"),
+                    span,
+                ));
+            }
+
+            let mut source = entry.item.source().clone();
+            source.make_absolute(self.base_dir);
+
+            // Variables other than `${PROFILE_DIR}` depend on where the profile is launched, and
+            // patterns that match nothing are allowed.
+            let is_pattern = entry.item.allows_pattern() && source.is_pattern();
+
+            if !source.has_variables() && !is_pattern && !source.exists() {
+                let span = entry
+                    .node
+                    .and_then(|node| node.find(|key| key == "path" || key == "source"))
+                    .map(|node| node.span.clone());
+
+                self.diagnostics.push(Diagnostic::error(
+                    format!("{kind} source `{}` does not exist", source.display()),
+                    span,
+                ));
+            }
+        }
+
+        let known = ids(inherited, shared, own);
+
+        for entry in own {
+            for (key, dependents) in [
+                ("load_before", entry.item.loads_before()),
+                ("load_after", entry.item.loads_after()),
+            ] {
+                for (index, dependent) in dependents.iter().enumerate() {
+                    let id = dependent.id();
+                    if known.contains(&id) || other_ids.contains(&id) {
+                        continue;
+                    }
+
+                    let span = entry
+                        .node
+                        .and_then(|node| node.get(key)?.index(index))
+                        .map(|node| node.get("id").unwrap_or(node).span.clone());
+
+                    let message = format!("{key} refers to unknown id `{id}`");
+
+                    self.diagnostics.push(if dependent.is_optional() {
+                        Diagnostic::warning(message, span)
+                    } else {
+                        Diagnostic::error(message, span)
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(source: &str, format: Format) -> Vec<(Severity, String)> {
+        validate_source(source, format, Path::new(env!("CARGO_MANIFEST_DIR")), None)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn savefile_names_must_be_valid_file_names() {
+        assert_eq!(check_savefile_name("modded.sl2"), Ok(()));
+        assert_eq!(
+            check_savefile_name("modded\x1f.sl2"),
+            Err(InvalidSavefileName::ReservedChar(
+                "modded\x1f.sl2".to_owned(),
+                '\x1f'
+            ))
+        );
+        assert_eq!(
+            check_savefile_name("modded."),
+            Err(InvalidSavefileName::TrailingDotOrSpace(
+                "modded.".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn valid_profile_has_no_diagnostics() {
+        let source = r#"
+profileVersion = "v1"
+savefile = "modded.sl2"
+
+[[packages]]
+id = "mod"
+path = "test-data"
+"#;
+
+        assert!(messages(source, Format::Toml).is_empty());
+    }
+
+    #[test]
+    fn reports_unknown_keys_with_location() {
+        let source = "profileVersion = \"v1\"\n\n[[packages]]\nid = \"mod\"\nsource = \"src\"\nenabeld = false\n";
+        let diagnostics = validate_source(
+            source,
+            Format::Toml,
+            Path::new(env!("CARGO_MANIFEST_DIR")),
+            None,
+        );
+
+        let validation = Validation {
+            path: PathBuf::from("test.me3"),
+            source: source.to_owned(),
+            diagnostics,
+        };
+
+        let unknown = &validation.diagnostics()[0];
+        assert_eq!(unknown.message, "unknown key `enabeld`");
+        assert_eq!(
+            validation.location(unknown.span.clone().unwrap().start),
+            Location { line: 6, column: 1 }
+        );
+    }
+
+    #[test]
+    fn reports_profile_problems() {
+        let source = r#"
+profileVersion = "v2"
+savefile = "modded?.sl2"
+
+[[packages]]
+id = "a"
+path = "test-data"
+load_after = [{ id = "b", optional = false }]
+
+[[packages]]
+id = "b"
+path = "missing-directory"
+load_after = [{ id = "a", optional = false }]
+
+[[packages]]
+id = "a"
+path = "test-data"
+load_after = [{ id = "b", optional = false }]
+load_before = [{ id = "unknown", optional = true }]
+"#;
+
+        let messages = messages(source, Format::Toml);
+
+        assert!(messages.contains(&(
+            Severity::Error,
+            "savefile name `modded?.sl2` contains reserved character '?'".to_owned()
+        )));
+        assert!(messages.contains(&(Severity::Error, "duplicate package id `a`".to_owned())));
+        assert!(messages
+            .iter()
+            .any(|(severity, message)| *severity == Severity::Error
+                && message.starts_with("package source")
+                && message.ends_with("missing-directory` does not exist")));
+        assert!(messages.contains(&(
+            Severity::Warning,
+            "load_before refers to unknown id `unknown`".to_owned()
+        )));
+        assert!(messages
+            .iter()
+            .any(|(_, message)| message.starts_with("dependencies form a cycle")));
+    }
+
+    #[test]
+    fn checks_natives_together_with_packages() {
+        let source = r#"
+profileVersion = "v2"
+
+[[natives]]
+id = "framework"
+path = "test-data"
+load_after = [{ id = "assets", optional = false }]
+
+[[packages]]
+id = "assets"
+path = "test-data"
+requires = [{ id = "framework" }]
+"#;
+
+        assert!(messages(source, Format::Toml).is_empty());
+
+        let source = r#"
+profileVersion = "v2"
+
+[[natives]]
+id = "framework"
+path = "test-data"
+load_before = [{ id = "assets", optional = false }]
+
+[[packages]]
+id = "assets"
+path = "test-data"
+load_before = [{ id = "framework", optional = false }]
+
+[[packages]]
+id = "framework"
+path = "test-data"
+"#;
+
+        assert_eq!(
+            messages(source, Format::Toml),
+            [(
+                Severity::Error,
+                "native id `framework` is also used by a package".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_incompatible_versions() {
+        let source = r#"
+profileVersion = "v2"
+
+[[packages]]
+id = "fixes"
+path = "test-data"
+metadata = { version = "1.1.0" }
+
+[[packages]]
+id = "overhaul"
+path = "test-data"
+load_after = [{ id = "fixes", optional = false, version = ">=1.2" }]
+"#;
+
+        assert_eq!(
+            messages(source, Format::Toml),
+            [(
+                Severity::Error,
+                "overhaul requires fixes >=1.2, but version 1.1.0 is loaded".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_conflicting_packages() {
+        let source = r#"
+profileVersion = "v2"
+
+[[packages]]
+id = "overhaul-a"
+path = "test-data"
+conflicts_with = [{ id = "overhaul-b" }]
+
+[[packages]]
+id = "overhaul-b"
+path = "test-data"
+"#;
+
+        assert_eq!(
+            messages(source, Format::Toml),
+            [(
+                Severity::Error,
+                "overhaul-a is incompatible with overhaul-b, which is loaded".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn checks_files_together_with_packages() {
+        let source = r#"
+profileVersion = "v2"
+
+[[packages]]
+id = "mod"
+path = "test-data"
+
+[[files]]
+id = "hard"
+source = "Cargo.toml"
+target = "data0:/regulation.bin"
+load_after = [{ id = "mod", optional = false }]
+
+[[files]]
+id = "mod"
+path = "Cargo.toml"
+target = "regulation.bin"
+"#;
+
+        assert_eq!(
+            messages(source, Format::Toml),
+            [(Severity::Error, "duplicate package id `mod`".to_owned())]
+        );
+    }
+
+    #[test]
+    fn reports_json_locations() {
+        let source = "{\n  \"profileVersion\": \"v1\",\n  \"natives\": [],\n  \"unknown\": 1\n}\n";
+        let diagnostics = validate_source(
+            source,
+            Format::Json,
+            Path::new(env!("CARGO_MANIFEST_DIR")),
+            None,
+        );
+
+        let validation = Validation {
+            path: PathBuf::from("test.me3.json"),
+            source: source.to_owned(),
+            diagnostics,
+        };
+
+        let unknown = &validation.diagnostics()[0];
+        assert_eq!(unknown.message, "unknown key `unknown`");
+        assert_eq!(
+            validation.location(unknown.span.clone().unwrap().start),
+            Location { line: 4, column: 3 }
+        );
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let diagnostics = validate_source(
+            "profileVersion = ",
+            Format::Toml,
+            Path::new(env!("CARGO_MANIFEST_DIR")),
+            None,
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].span.is_some());
+    }
+}
@@ -0,0 +1,204 @@
+//! Expansion of variables in the paths of natives, packages and files, so that profiles can refer
+//! to locations that differ between machines.
+//!
+//! Paths can contain `${NAME}`, which is replaced by one of the built-in variables below or by the
+//! environment variable with that name, and can start with `~`, which is replaced by the home
+//! directory.
+
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    path::{Component, Path, PathBuf},
+};
+
+use thiserror::Error;
+
+/// The directory containing the game executable.
+pub const GAME_DIR: &str = "GAME_DIR";
+
+/// The directory containing the profile the path was written in.
+pub const PROFILE_DIR: &str = "PROFILE_DIR";
+
+/// The directory me3 stores its data, like logs, in.
+pub const ME3_DATA_DIR: &str = "ME3_DATA_DIR";
+
+/// The user's Documents folder (`FOLDERID_Documents`), only defined in the game process for the
+/// savefile directories of games.
+pub const DOCUMENTS_DIR: &str = "DOCUMENTS_DIR";
+
+const BUILTINS: &[(&str, &str)] = &[
+    (GAME_DIR, "game directory"),
+    (PROFILE_DIR, "profile directory"),
+    (ME3_DATA_DIR, "me3 data directory"),
+];
+
+#[derive(Debug, Error)]
+pub enum ExpandError {
+    #[error("{} uses ${{{name}}}, but the {description} is not known", path.display())]
+    Unavailable {
+        name: String,
+        description: &'static str,
+        path: PathBuf,
+    },
+
+    #[error(
+        "{} uses ${{{name}}}, but no environment variable named {name} is set",
+        path.display()
+    )]
+    Undefined { name: String, path: PathBuf },
+
+    #[error("{} starts with ~, but the home directory is not known", .0.display())]
+    NoHome(PathBuf),
+
+    #[error("{} contains a `${{` without a matching `}}`", .0.display())]
+    Unterminated(PathBuf),
+}
+
+/// The values of the built-in variables, and how to look up everything else.
+#[derive(Clone, Debug, Default)]
+pub struct PathVariables {
+    values: HashMap<String, PathBuf>,
+    home: Option<PathBuf>,
+}
+
+impl PathVariables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of a variable, e.g. [`GAME_DIR`], taking precedence over the environment.
+    pub fn set(&mut self, name: &str, value: impl Into<PathBuf>) -> &mut Self {
+        self.values.insert(name.to_owned(), value.into());
+        self
+    }
+
+    /// Sets the directory `~` is replaced with.
+    pub fn set_home(&mut self, home: impl Into<PathBuf>) -> &mut Self {
+        self.home = Some(home.into());
+        self
+    }
+
+    /// Whether `path` contains anything that is replaced by [`PathVariables::expand`].
+    pub fn contains_variables(path: &Path) -> bool {
+        path.to_str().is_some_and(|path| path.contains("${")) || starts_with_home(path)
+    }
+
+    /// Replaces the variables in `path` with their values.
+    pub fn expand(&self, path: &Path) -> Result<PathBuf, ExpandError> {
+        let Some(mut rest) = path.to_str() else {
+            // Paths read from profiles are always valid UTF-8.
+            return Ok(path.to_path_buf());
+        };
+
+        let mut expanded = OsString::new();
+
+        if starts_with_home(path) {
+            let home = self
+                .home
+                .as_ref()
+                .ok_or_else(|| ExpandError::NoHome(path.to_path_buf()))?;
+
+            expanded.push(home);
+            rest = &rest[1..];
+        }
+
+        while let Some(start) = rest.find("${") {
+            expanded.push(&rest[..start]);
+
+            let Some(len) = rest[start + 2..].find('}') else {
+                return Err(ExpandError::Unterminated(path.to_path_buf()));
+            };
+
+            let name = &rest[start + 2..start + 2 + len];
+            expanded.push(self.lookup(name, path)?);
+
+            rest = &rest[start + len + 3..];
+        }
+
+        expanded.push(rest);
+
+        Ok(PathBuf::from(expanded))
+    }
+
+    fn lookup(&self, name: &str, path: &Path) -> Result<OsString, ExpandError> {
+        if let Some(value) = self.values.get(name) {
+            return Ok(value.clone().into_os_string());
+        }
+
+        if let Some((_, description)) = BUILTINS.iter().find(|(builtin, _)| *builtin == name) {
+            return Err(ExpandError::Unavailable {
+                name: name.to_owned(),
+                description,
+                path: path.to_path_buf(),
+            });
+        }
+
+        env::var_os(name).ok_or_else(|| ExpandError::Undefined {
+            name: name.to_owned(),
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+fn starts_with_home(path: &Path) -> bool {
+    path.components()
+        .next()
+        .is_some_and(|component| component == Component::Normal("~".as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{ExpandError, PathVariables, GAME_DIR, ME3_DATA_DIR};
+
+    fn variables() -> PathVariables {
+        let mut variables = PathVariables::new();
+        variables.set(GAME_DIR, "/games/ELDEN RING/Game");
+        variables.set_home("/home/tarnished");
+        variables.set("MOD_NAME", "overhaul");
+        variables
+    }
+
+    #[test]
+    fn expands_variables() {
+        let variables = variables();
+        let expand = |path: &str| variables.expand(Path::new(path)).unwrap();
+
+        assert_eq!(
+            expand("${GAME_DIR}/mods/overhaul"),
+            PathBuf::from("/games/ELDEN RING/Game/mods/overhaul")
+        );
+        assert_eq!(
+            expand("~/mods/${MOD_NAME}.dll"),
+            PathBuf::from("/home/tarnished/mods/overhaul.dll")
+        );
+        assert_eq!(expand("~tarnished/mods"), PathBuf::from("~tarnished/mods"));
+        assert_eq!(expand("costs $5/mods"), PathBuf::from("costs $5/mods"));
+    }
+
+    #[test]
+    fn reports_undefined_variables() {
+        let variables = variables();
+        let expand = |path: &str| variables.expand(Path::new(path)).unwrap_err();
+
+        assert!(matches!(
+            expand("${ME3_DATA_DIR}/mods"),
+            ExpandError::Unavailable { name, .. } if name == ME3_DATA_DIR
+        ));
+        assert_eq!(
+            expand("${ME3_TEST_UNDEFINED}/mods").to_string(),
+            "${ME3_TEST_UNDEFINED}/mods uses ${ME3_TEST_UNDEFINED}, but no environment variable \
+             named ME3_TEST_UNDEFINED is set"
+        );
+        assert!(matches!(
+            expand("${GAME_DIR/mods"),
+            ExpandError::Unterminated(_)
+        ));
+        assert!(matches!(
+            PathVariables::new().expand(Path::new("~/mods")),
+            Err(ExpandError::NoHome(_))
+        ));
+    }
+}
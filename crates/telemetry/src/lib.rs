@@ -162,6 +162,15 @@ pub fn trace_id() -> Option<String> {
     None
 }
 
+/// Attaches a tag to every event reported from now on.
+pub fn set_tag(key: &str, value: &str) {
+    #[cfg(feature = "sentry")]
+    sentry::Hub::main().configure_scope(|scope| scope.set_tag(key, value));
+
+    #[cfg(not(feature = "sentry"))]
+    let _ = (key, value);
+}
+
 impl TelemetryConfig {
     pub fn with_console_writer<W2>(self, writer: W2) -> Self
     where